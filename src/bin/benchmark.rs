@@ -0,0 +1,315 @@
+//! Steps every demo headlessly and reports how long each physics tick takes.
+//!
+//! cargo run --release --bin benchmark -- --output benchmarks/latest.json
+//! cargo run --release --bin benchmark -- --baseline benchmarks/baseline.json
+//! cargo run --release --bin benchmark -- --scale 10 --baseline benchmarks/scale10.json
+use bevy_physics_lab::game::{
+    demo_state::{enter_demo_now, DemoState},
+    headless::{HeadlessLab, HEADLESS_TICK_HZ},
+    physics_demos::{
+        colliders_demo::CollidersParams, conveyor_belt_demo::ConveyorBeltParams,
+        destructible_demo::DestructibleParams, joint_demo::JointParams, magnet_demo::MagnetParams,
+    },
+};
+
+use bevy::{
+    ecs::query::QueryFilter,
+    prelude::*,
+    utils::{Duration, Instant},
+};
+use bevy_xpbd_2d::prelude::*;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+// CLI -------------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Parser)]
+#[command(about = "Benchmark physics step time across the demos")]
+struct Cli {
+    /// Demos to run, all of them if omitted
+    #[arg(long, value_enum)]
+    demo: Vec<DemoState>,
+
+    /// Timed ticks per demo
+    #[arg(long, default_value_t = 600)]
+    ticks: u32,
+
+    /// Untimed ticks per demo before timing starts, lets bodies settle
+    #[arg(long, default_value_t = 60)]
+    warmup: u32,
+
+    /// Stress-test scale given to every demo's tuning, multiplies their body counts
+    #[arg(long, default_value_t = 1.0, value_parser = parse_scale)]
+    scale: f32,
+
+    /// Where to write the JSON results
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// JSON results of an earlier run to compare against
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Mean step time increase over the baseline, in percent, counted as a regression
+    #[arg(long, default_value_t = 10.0)]
+    tolerance: f64,
+
+    /// Exit with an error if any demo regressed against the baseline
+    #[arg(long, requires = "baseline")]
+    fail_on_regression: bool,
+}
+
+fn parse_scale(arg: &str) -> Result<f32, String> {
+    let scale: f32 = arg.parse().map_err(|err| format!("{err}"))?;
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(format!("scale must be a positive number, got {arg}"));
+    }
+    Ok(scale)
+}
+
+// report ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Serialize, Deserialize)]
+struct BenchmarkReport {
+    ticks: u32,
+    warmup: u32,
+    tick_hz: f64,
+    /// reports written before `--scale` existed ran at scale 1
+    #[serde(default = "default_scale")]
+    scale: f32,
+    results: Vec<DemoResult>,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// Time spent stepping physics each tick in milliseconds, counts taken after the last tick
+#[derive(Serialize, Deserialize)]
+struct DemoResult {
+    demo: DemoState,
+    mean_ms: f64,
+    p50_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+    bodies: usize,
+    contacts: usize,
+    joints: usize,
+}
+
+/// Nearest-rank percentile of already sorted samples
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (percent / 100.0 * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+fn count<F: QueryFilter>(world: &mut World) -> usize {
+    world.query_filtered::<(), F>().iter(world).count()
+}
+
+/// Time spent in `PhysicsSet::StepSimulation` since it was last taken, the rest of the
+/// update (demo systems, input, transform propagation) isn't counted
+#[derive(Resource, Default)]
+struct StepTimer {
+    started: Option<Instant>,
+    elapsed: Duration,
+}
+
+fn start_step_timer(mut timer: ResMut<StepTimer>) {
+    timer.started = Some(Instant::now());
+}
+
+fn stop_step_timer(mut timer: ResMut<StepTimer>) {
+    if let Some(started) = timer.started.take() {
+        timer.elapsed += started.elapsed();
+    }
+}
+
+/// Gives every demo's tuning the same scale, then restarts the demo so it spawns at it
+fn set_scale(lab: &mut HeadlessLab, demo: DemoState, scale: f32) {
+    let world = lab.world();
+    world.resource_mut::<CollidersParams>().scale = scale;
+    world.resource_mut::<ConveyorBeltParams>().scale = scale;
+    world.resource_mut::<DestructibleParams>().scale = scale;
+    world.resource_mut::<JointParams>().scale = scale;
+    world.resource_mut::<MagnetParams>().scale = scale;
+    enter_demo_now(world, demo);
+}
+
+fn run_demo(demo: DemoState, warmup: u32, ticks: u32, scale: f32) -> DemoResult {
+    let mut lab = HeadlessLab::new(demo);
+    if scale != 1.0 {
+        set_scale(&mut lab, demo, scale);
+    }
+    lab.app.init_resource::<StepTimer>().add_systems(
+        PostUpdate,
+        (
+            start_step_timer
+                .after(PhysicsSet::Prepare)
+                .before(PhysicsSet::StepSimulation),
+            stop_step_timer
+                .after(PhysicsSet::StepSimulation)
+                .before(PhysicsSet::Sync),
+        ),
+    );
+    lab.run_ticks(warmup);
+
+    let mut samples: Vec<f64> = (0..ticks)
+        .map(|_| {
+            lab.world().resource_mut::<StepTimer>().elapsed = Duration::ZERO;
+            lab.run_ticks(1);
+            lab.world().resource::<StepTimer>().elapsed.as_secs_f64() * 1000.0
+        })
+        .collect();
+    samples.sort_by(f64::total_cmp);
+    let mean_ms = samples.iter().sum::<f64>() / samples.len().max(1) as f64;
+
+    let world = lab.world();
+    let contacts = world
+        .resource::<Collisions>()
+        .iter()
+        .filter(|contacts| contacts.during_current_frame)
+        .count();
+    let joints = count::<With<FixedJoint>>(world)
+        + count::<With<DistanceJoint>>(world)
+        + count::<With<PrismaticJoint>>(world)
+        + count::<With<RevoluteJoint>>(world)
+        + count::<With<SphericalJoint>>(world);
+
+    DemoResult {
+        demo,
+        mean_ms,
+        p50_ms: percentile(&samples, 50.0),
+        p95_ms: percentile(&samples, 95.0),
+        p99_ms: percentile(&samples, 99.0),
+        max_ms: samples.last().copied().unwrap_or_default(),
+        bodies: count::<With<RigidBody>>(world),
+        contacts,
+        joints,
+    }
+}
+
+fn write_report(report: &BenchmarkReport, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(report)?)?;
+    Ok(())
+}
+
+fn read_report(path: &Path) -> Result<BenchmarkReport, Box<dyn Error>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Prints each demo's change against the baseline, returns the demos that regressed
+fn compare(report: &BenchmarkReport, baseline: &BenchmarkReport, tolerance: f64) -> Vec<DemoState> {
+    let change = |new: f64, old: f64| {
+        if old > 0.0 {
+            (new - old) / old * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    println!();
+    println!("{:<14} {:>10} {:>10}", "vs baseline", "mean", "p95");
+    let mut regressions = Vec::new();
+    for result in &report.results {
+        let Some(old) = baseline.results.iter().find(|old| old.demo == result.demo) else {
+            println!("{:<14} {:>10}", format!("{:?}", result.demo), "new");
+            continue;
+        };
+        let mean_change = change(result.mean_ms, old.mean_ms);
+        let regressed = mean_change > tolerance;
+        println!(
+            "{:<14} {:>9.1}% {:>9.1}%{}",
+            format!("{:?}", result.demo),
+            mean_change,
+            change(result.p95_ms, old.p95_ms),
+            if regressed { "  REGRESSION" } else { "" }
+        );
+        if regressed {
+            regressions.push(result.demo);
+        }
+    }
+    regressions
+}
+
+// MAIN ------------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    let cli = Cli::parse();
+    let demos = if cli.demo.is_empty() {
+        DemoState::value_variants().to_vec()
+    } else {
+        cli.demo.clone()
+    };
+
+    println!(
+        "{:<14} {:>8} {:>8} {:>8} {:>8} {:>8} {:>7} {:>8} {:>7}",
+        "demo", "mean ms", "p50", "p95", "p99", "max", "bodies", "contacts", "joints"
+    );
+    let mut results = Vec::new();
+    for demo in demos {
+        let result = run_demo(demo, cli.warmup, cli.ticks, cli.scale);
+        println!(
+            "{:<14} {:>8.3} {:>8.3} {:>8.3} {:>8.3} {:>8.3} {:>7} {:>8} {:>7}",
+            format!("{demo:?}"),
+            result.mean_ms,
+            result.p50_ms,
+            result.p95_ms,
+            result.p99_ms,
+            result.max_ms,
+            result.bodies,
+            result.contacts,
+            result.joints
+        );
+        results.push(result);
+    }
+
+    let report = BenchmarkReport {
+        ticks: cli.ticks,
+        warmup: cli.warmup,
+        tick_hz: HEADLESS_TICK_HZ,
+        scale: cli.scale,
+        results,
+    };
+    if let Some(output) = &cli.output {
+        write_report(&report, output)?;
+        println!("\nwrote results to {}", output.display());
+    }
+
+    let Some(baseline_path) = &cli.baseline else {
+        return Ok(ExitCode::SUCCESS);
+    };
+    let baseline = read_report(baseline_path)?;
+    if baseline.ticks != report.ticks || baseline.warmup != report.warmup {
+        println!(
+            "\nnote: baseline ran {} ticks after {} warmup, this run {} after {}",
+            baseline.ticks, baseline.warmup, report.ticks, report.warmup
+        );
+    }
+    if baseline.scale != report.scale {
+        println!(
+            "\nnote: baseline ran at scale {}, this run at {}",
+            baseline.scale, report.scale
+        );
+    }
+    let regressions = compare(&report, &baseline, cli.tolerance);
+
+    if cli.fail_on_regression && !regressions.is_empty() {
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
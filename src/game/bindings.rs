@@ -0,0 +1,356 @@
+use super::player::{Player, PlayerAction};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use leafwing_input_manager::{prelude::*, user_input::InputKind};
+use serde::{Deserialize, Serialize};
+
+use std::{error::Error, fs, path::Path};
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub const DEFAULT_BINDINGS_PATH: &str = "config/bindings.ron";
+
+/// Actions that can't be rebound: `Move` is an axis, and losing the key for this screen
+/// would leave no way back into it
+const FIXED_ACTIONS: [PlayerAction; 2] = [PlayerAction::Move, PlayerAction::ShowBindings];
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Loads bindings from disk and adds the rebinding screen. `PlayerBindings` itself is
+/// initialized by the simulation, which runs on the defaults.
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<BindingsScreen>()
+            // PreStartup ------------------------------------------------------
+            // before the first demo spawns the player
+            .add_systems(PreStartup, load_bindings)
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    toggle_bindings_screen,
+                    capture_rebind_input,
+                    bindings_screen_ui,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// The input map given to the player whenever it spawns
+#[derive(Resource, Clone)]
+pub struct PlayerBindings(pub InputMap<PlayerAction>);
+
+impl Default for PlayerBindings {
+    fn default() -> Self {
+        Self(PlayerAction::default_input_map())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BindingsScreen {
+    pub open: bool,
+    /// the action waiting for its new key or gamepad button
+    pub listening: Option<PlayerAction>,
+    /// why the last key or button pressed while listening was turned down
+    pub rejected: Option<String>,
+}
+
+// bindings file ---------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// What `write_bindings` saves
+#[derive(Serialize, Deserialize)]
+struct BindingsFile {
+    /// every action there was when the file was written, actions added since get their
+    /// defaults and cleared ones stay cleared
+    actions: Vec<PlayerAction>,
+    input_map: InputMap<PlayerAction>,
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn write_bindings(
+    bindings: &InputMap<PlayerAction>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = BindingsFile {
+        actions: PlayerAction::ALL.to_vec(),
+        input_map: bindings.clone(),
+    };
+    let ron = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?;
+    fs::write(path, ron)?;
+    Ok(())
+}
+
+/// Reads saved bindings over the defaults, action by action
+pub fn read_bindings(path: &Path) -> Result<InputMap<PlayerAction>, Box<dyn Error>> {
+    let ron = fs::read_to_string(path)?;
+    let file = match ron::from_str::<BindingsFile>(&ron) {
+        Ok(file) => file,
+        // files from before the action list was saved hold just the input map
+        Err(_) => {
+            let input_map: InputMap<PlayerAction> = ron::from_str(&ron)?;
+            BindingsFile {
+                actions: PlayerAction::ALL
+                    .into_iter()
+                    .filter(|action| input_map.get(action).is_some_and(|bound| !bound.is_empty()))
+                    .collect(),
+                input_map,
+            }
+        }
+    };
+
+    let mut input_map = PlayerAction::default_input_map();
+    for action in file.actions {
+        input_map.clear_action(&action);
+        for input in file.input_map.get(&action).into_iter().flatten() {
+            input_map.insert(action, input.clone());
+        }
+    }
+    Ok(input_map)
+}
+
+fn is_gamepad_input(input: &UserInput) -> bool {
+    matches!(input, UserInput::Single(InputKind::GamepadButton(_)))
+}
+
+/// Whether `bound` already uses the key or button `input` stands for
+fn uses_input(bound: &UserInput, input: &UserInput) -> bool {
+    match (bound, input) {
+        (UserInput::VirtualDPad(dpad), UserInput::Single(kind)) => {
+            [&dpad.up, &dpad.down, &dpad.left, &dpad.right].contains(&kind)
+        }
+        (bound, input) => bound == input,
+    }
+}
+
+/// The action other than `action` that `input` is bound to, if any
+fn bound_elsewhere(
+    input_map: &InputMap<PlayerAction>,
+    action: PlayerAction,
+    input: &UserInput,
+) -> Option<PlayerAction> {
+    PlayerAction::ALL
+        .into_iter()
+        .filter(|other| *other != action)
+        .find(|other| {
+            input_map
+                .get(other)
+                .into_iter()
+                .flatten()
+                .any(|bound| uses_input(bound, input))
+        })
+}
+
+/// Whether any action is bound to `key`
+pub fn binds_key(input_map: &InputMap<PlayerAction>, key: KeyCode) -> bool {
+    let input = UserInput::from(key);
+    PlayerAction::ALL.into_iter().any(|action| {
+        input_map
+            .get(&action)
+            .into_iter()
+            .flatten()
+            .any(|bound| uses_input(bound, &input))
+    })
+}
+
+fn input_label(input: &UserInput) -> String {
+    match input {
+        UserInput::Single(InputKind::PhysicalKey(key)) => format!("{key:?}"),
+        UserInput::Single(InputKind::GamepadButton(button)) => format!("Pad {button:?}"),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Binds `input` to `action`, replacing the action's previous input of the same kind
+/// (keyboard or gamepad) so each device keeps one binding per action
+fn rebind(input_map: &mut InputMap<PlayerAction>, action: PlayerAction, input: UserInput) {
+    let kept: Vec<UserInput> = input_map
+        .get(&action)
+        .into_iter()
+        .flatten()
+        .filter(|bound| is_gamepad_input(bound) != is_gamepad_input(&input))
+        .cloned()
+        .collect();
+
+    input_map.clear_action(&action);
+    for bound in kept {
+        input_map.insert(action, bound);
+    }
+    input_map.insert(action, input);
+}
+
+/// Hands the bindings to the live player and saves them
+fn apply_bindings(
+    bindings: &PlayerBindings,
+    player_query: &mut Query<&mut InputMap<PlayerAction>, With<Player>>,
+) {
+    for mut input_map in player_query.iter_mut() {
+        *input_map = bindings.0.clone();
+    }
+    match write_bindings(&bindings.0, Path::new(DEFAULT_BINDINGS_PATH)) {
+        Ok(()) => info!("saved bindings to {DEFAULT_BINDINGS_PATH}"),
+        Err(err) => error!("failed to save bindings to {DEFAULT_BINDINGS_PATH}: {err}"),
+    }
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn load_bindings(mut bindings: ResMut<PlayerBindings>) {
+    let path = Path::new(DEFAULT_BINDINGS_PATH);
+    // no file yet just means nothing has been rebound
+    if !path.exists() {
+        return;
+    }
+    match read_bindings(path) {
+        Ok(input_map) => bindings.0 = input_map,
+        Err(err) => {
+            error!("failed to load bindings from {DEFAULT_BINDINGS_PATH}, using defaults: {err}")
+        }
+    }
+}
+
+pub fn toggle_bindings_screen(
+    mut screen: ResMut<BindingsScreen>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    if action_state.just_pressed(&PlayerAction::ShowBindings) {
+        screen.open = !screen.open;
+        screen.listening = None;
+        screen.rejected = None;
+    }
+}
+
+/// Takes the next key or gamepad button pressed while an action is listening, escape cancels.
+/// Inputs already bound to another action are turned down.
+pub fn capture_rebind_input(
+    mut screen: ResMut<BindingsScreen>,
+    mut bindings: ResMut<PlayerBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut player_query: Query<&mut InputMap<PlayerAction>, With<Player>>,
+    mut action_query: Query<&mut ActionState<PlayerAction>, With<Player>>,
+) {
+    let Some(action) = screen.listening else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        screen.listening = None;
+        screen.rejected = None;
+        return;
+    }
+
+    let input = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| UserInput::from(*key))
+        .or_else(|| {
+            gamepad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| UserInput::from(button.button_type))
+        });
+    let Some(input) = input else {
+        return;
+    };
+
+    if let Some(other) = bound_elsewhere(&bindings.0, action, &input) {
+        let reason = format!("{} is already bound to {other:?}", input_label(&input));
+        warn!("not rebinding {action:?}: {reason}");
+        screen.rejected = Some(reason);
+        return;
+    }
+
+    rebind(&mut bindings.0, action, input);
+    screen.listening = None;
+    screen.rejected = None;
+    apply_bindings(&bindings, &mut player_query);
+    // the new key is still down, it shouldn't count as a press until it's pressed again
+    for mut action_state in action_query.iter_mut() {
+        action_state.consume(&action);
+    }
+}
+
+pub fn bindings_screen_ui(
+    mut contexts: EguiContexts,
+    mut screen: ResMut<BindingsScreen>,
+    mut bindings: ResMut<PlayerBindings>,
+    mut player_query: Query<&mut InputMap<PlayerAction>, With<Player>>,
+) {
+    if !screen.open {
+        return;
+    }
+
+    let mut open = screen.open;
+    let mut changed = false;
+    egui::Window::new("Bindings")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("bindings_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for action in PlayerAction::ALL {
+                        ui.label(format!("{action:?}"));
+                        let labels: Vec<String> = bindings
+                            .0
+                            .get(&action)
+                            .into_iter()
+                            .flatten()
+                            .map(input_label)
+                            .collect();
+                        ui.label(labels.join(", "));
+
+                        if FIXED_ACTIONS.contains(&action) {
+                            ui.label("fixed");
+                        } else if screen.listening == Some(action) {
+                            match &screen.rejected {
+                                Some(reason) => ui.label(format!("{reason}, try another")),
+                                None => ui.label("press a key or button, esc cancels"),
+                            };
+                        } else {
+                            ui.horizontal(|ui| {
+                                if ui.button("Rebind").clicked() {
+                                    screen.listening = Some(action);
+                                    screen.rejected = None;
+                                }
+                                if ui.button("Clear").clicked() {
+                                    bindings.0.clear_action(&action);
+                                    changed = true;
+                                }
+                            });
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            if ui.button("Defaults").clicked() {
+                *bindings = PlayerBindings::default();
+                screen.listening = None;
+                screen.rejected = None;
+                changed = true;
+            }
+        });
+    screen.open = open;
+    if !open {
+        screen.listening = None;
+        screen.rejected = None;
+    }
+
+    if changed {
+        apply_bindings(&bindings, &mut player_query);
+    }
+}
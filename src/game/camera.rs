@@ -1,30 +1,150 @@
-use super::player;
+use super::{
+    demo_state::{DemoRegistry, DemoScoped, DemoState},
+    player::{Player, PlayerAction},
+};
 
 use bevy::{
     core_pipeline::{
         bloom::{BloomPrefilterSettings, BloomSettings},
         tonemapping::Tonemapping,
     },
+    input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
     render::{
         camera::{CameraOutputMode, ScalingMode},
         render_resource::{BlendState, LoadOp},
     },
+    window::PrimaryWindow,
 };
+use bevy_inspector_egui::{bevy_egui::EguiContexts, prelude::*};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// World units visible at a projection scale of 1
+pub const VIEW_SIZE: Vec2 = Vec2::new(256.0, 144.0);
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 20.0;
+/// Scale change per mouse wheel line
+const ZOOM_STEP: f32 = 0.1;
+/// Empty space left around the demo when fitting the camera to it
+const FIT_MARGIN: f32 = 1.1;
+/// Longest frame the follow spring integrates in one step, keeps it stable through hitches
+const MAX_FOLLOW_DELTA: f32 = 1.0 / 20.0;
 
 // components ------------------------------------------------------------------
 // -----------------------------------------------------------------------------
 #[derive(Component)]
 pub struct DemoCamera;
 
-// events ----------------------------------------------------------------------
+// resources -------------------------------------------------------------------
 // -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CameraMode {
+    #[default]
+    FollowPlayer,
+    /// Springs to a point requested with `CameraTranslation`
+    PointOfInterest(Vec2),
+    /// Detached from the player, moved only by panning and fitting
+    Free,
+}
+
+#[derive(Resource, Default)]
+pub struct CameraControls {
+    pub mode: CameraMode,
+    /// world point held under the cursor while middle-dragging
+    pub pan_anchor: Option<Vec2>,
+    /// the running demo's `Demo::CAMERA_BOUNDS`
+    pub bounds: Option<Rect>,
+    /// where the follow spring pulls the camera, only moved once the player leaves the dead zone
+    pub follow_target: Vec2,
+    pub follow_velocity: Vec2,
+    /// jump straight to the target next frame instead of springing there, e.g. after a demo change
+    pub snap: bool,
+}
 
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct CameraFollowSettings {
+    /// spring stiffness, higher catches up faster
+    #[inspector(min = 0.0)]
+    pub stiffness: f32,
+    /// 1 is critically damped, lower overshoots
+    #[inspector(min = 0.0)]
+    pub damping_ratio: f32,
+    /// seconds of player velocity the camera leads by
+    #[inspector(min = 0.0)]
+    pub look_ahead: f32,
+    #[inspector(min = 0.0)]
+    pub max_look_ahead: f32,
+    /// half extents of the box the player can move in without moving the camera
+    pub dead_zone: Vec2,
+}
+
+impl Default for CameraFollowSettings {
+    fn default() -> Self {
+        Self {
+            stiffness: 40.0,
+            damping_ratio: 1.0,
+            look_ahead: 0.3,
+            max_look_ahead: 40.0,
+            dead_zone: Vec2::new(10.0, 8.0),
+        }
+    }
+}
+
+/// The cursor in world space, shared by everything the mouse interacts with
+#[derive(Resource, Default)]
+pub struct WorldCursor {
+    pub position: Option<Vec2>,
+    /// world units per second, smoothed over a few frames
+    pub velocity: Vec2,
+    /// the cursor is over an egui window, clicks belong to the UI
+    pub over_ui: bool,
+}
+
+// events ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Springs the camera over to `position` and holds it there until the free camera is toggled
 #[derive(Event)]
 pub struct CameraTranslation {
     pub position: Vec2,
 }
 
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// World position under the cursor, `None` if the cursor is outside the window
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+}
+
+/// Moves `center` so a view of `half_view` stays inside `bounds`, centering it on an axis
+/// where the view is larger than the bounds
+pub fn clamp_view_center(center: Vec2, half_view: Vec2, bounds: Rect) -> Vec2 {
+    let min = bounds.min + half_view;
+    let max = bounds.max - half_view;
+    Vec2::new(
+        if min.x <= max.x {
+            center.x.clamp(min.x, max.x)
+        } else {
+            bounds.center().x
+        },
+        if min.y <= max.y {
+            center.y.clamp(min.y, max.y)
+        } else {
+            bounds.center().y
+        },
+    )
+}
+
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
 pub fn spawn_camera(mut commands: Commands) {
@@ -44,8 +164,8 @@ pub fn spawn_camera(mut commands: Commands) {
             projection: OrthographicProjection {
                 near: -1.0,
                 scaling_mode: ScalingMode::Fixed {
-                    width: 256.0,
-                    height: 144.0,
+                    width: VIEW_SIZE.x,
+                    height: VIEW_SIZE.y,
                 },
                 ..default()
             },
@@ -63,16 +183,224 @@ pub fn spawn_camera(mut commands: Commands) {
     ));
 }
 
+pub fn camera_follows_target(controls: Res<CameraControls>) -> bool {
+    controls.mode != CameraMode::Free
+}
+
+/// Runs whenever a demo is entered or restarted
+pub fn reset_camera_for_demo(
+    mut controls: ResMut<CameraControls>,
+    demo_state: Res<State<DemoState>>,
+    registry: Res<DemoRegistry>,
+) {
+    controls.bounds = registry
+        .get(*demo_state.get())
+        .and_then(|demo| demo.camera_bounds);
+    if let CameraMode::PointOfInterest(_) = controls.mode {
+        controls.mode = CameraMode::FollowPlayer;
+    }
+    controls.snap = true;
+}
+
+pub fn handle_camera_translations(
+    mut events: EventReader<CameraTranslation>,
+    mut controls: ResMut<CameraControls>,
+) {
+    if let Some(event) = events.read().last() {
+        controls.mode = CameraMode::PointOfInterest(event.position);
+    }
+}
+
+/// Pulls the camera towards the player (leading it by its velocity) or a point of interest
+/// on a damped spring
 pub fn camera_follow_player(
-    player_query: Query<&Transform, With<player::Player>>,
-    mut camera_query: Query<&mut Transform, (With<Camera>, Without<player::Player>)>,
+    time: Res<Time>,
+    settings: Res<CameraFollowSettings>,
+    mut controls: ResMut<CameraControls>,
+    player_query: Query<(&Transform, &LinearVelocity), With<Player>>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), Without<Player>>,
+) {
+    let Ok((mut camera_transform, projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    match controls.mode {
+        CameraMode::FollowPlayer => {
+            let Ok((player_transform, linear_velocity)) = player_query.get_single() else {
+                return;
+            };
+            let look_ahead =
+                (linear_velocity.0 * settings.look_ahead).clamp_length_max(settings.max_look_ahead);
+            let desired = player_transform.translation.truncate() + look_ahead;
+
+            if controls.snap {
+                controls.follow_target = desired;
+            } else {
+                // drag the target along only by as much as the player left the dead zone
+                let offset = desired - controls.follow_target;
+                let excess = (offset.abs() - settings.dead_zone).max(Vec2::ZERO);
+                controls.follow_target += excess * offset.signum();
+            }
+        }
+        CameraMode::PointOfInterest(position) => controls.follow_target = position,
+        CameraMode::Free => return,
+    }
+
+    let mut target = controls.follow_target;
+    if let Some(bounds) = controls.bounds {
+        target = clamp_view_center(target, VIEW_SIZE * projection.scale / 2.0, bounds);
+    }
+
+    let mut position = camera_transform.translation.truncate();
+    if controls.snap {
+        position = target;
+        controls.follow_velocity = Vec2::ZERO;
+        controls.snap = false;
+    } else {
+        let delta_secs = time.delta_seconds().min(MAX_FOLLOW_DELTA);
+        let damping = 2.0 * settings.damping_ratio * settings.stiffness.sqrt();
+        let acceleration =
+            settings.stiffness * (target - position) - damping * controls.follow_velocity;
+        controls.follow_velocity += acceleration * delta_secs;
+        position += controls.follow_velocity * delta_secs;
+    }
+
+    // don't change the current depth (z) value
+    camera_transform.translation = position.extend(camera_transform.translation.z);
+}
+
+pub fn update_world_cursor(
+    time: Res<Time>,
+    mut cursor: ResMut<WorldCursor>,
+    mut contexts: EguiContexts,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<DemoCamera>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    let position = cursor_world_position(window, camera, camera_transform);
+    let delta_secs = time.delta_seconds();
+    if let (Some(previous), Some(current)) = (cursor.position, position) {
+        if delta_secs > 0.0 {
+            let velocity = (current - previous) / delta_secs;
+            cursor.velocity = cursor.velocity.lerp(velocity, 0.5);
+        }
+    } else {
+        cursor.velocity = Vec2::ZERO;
+    }
+    cursor.position = position;
+    cursor.over_ui = contexts.ctx_mut().is_pointer_over_area();
+}
+
+pub fn toggle_camera_mode(
+    mut controls: ResMut<CameraControls>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
 ) {
-    let player_transform = player_query.single();
-    let mut camera_transform = camera_query.single_mut();
-
-    camera_transform.translation = Vec3::new(
-        player_transform.translation.x,
-        player_transform.translation.y,
-        camera_transform.translation.z, // don't change the current depth (z) value
-    );
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    if action_state.just_pressed(&PlayerAction::ToggleFreeCamera) {
+        controls.mode = match controls.mode {
+            CameraMode::FollowPlayer => CameraMode::Free,
+            CameraMode::PointOfInterest(_) | CameraMode::Free => CameraMode::FollowPlayer,
+        };
+    }
+}
+
+/// Zooms around the cursor, keeping the world point under it in place
+pub fn zoom_camera(
+    mut wheel_events: EventReader<MouseWheel>,
+    cursor: Res<WorldCursor>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<DemoCamera>>,
+) {
+    let lines: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // roughly one line per 20 pixels of touchpad scroll
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    if lines == 0.0 || cursor.over_ui {
+        return;
+    }
+    let Ok((mut transform, mut projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let old_scale = projection.scale;
+    projection.scale = (old_scale * (1.0 - ZOOM_STEP).powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+
+    if let Some(focus) = cursor.position {
+        let camera_position = transform.translation.truncate();
+        let new_position = focus + (camera_position - focus) * projection.scale / old_scale;
+        transform.translation = new_position.extend(transform.translation.z);
+    }
+}
+
+/// Middle-drag drags the world along with the cursor, detaching the camera from the player
+pub fn pan_camera(
+    mut controls: ResMut<CameraControls>,
+    cursor: Res<WorldCursor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut camera_query: Query<&mut Transform, With<DemoCamera>>,
+) {
+    if mouse.just_pressed(MouseButton::Middle) && !cursor.over_ui {
+        controls.pan_anchor = cursor.position;
+    }
+    if !mouse.pressed(MouseButton::Middle) {
+        controls.pan_anchor = None;
+        return;
+    }
+    let (Some(anchor), Some(cursor_position), Ok(mut transform)) = (
+        controls.pan_anchor,
+        cursor.position,
+        camera_query.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    controls.mode = CameraMode::Free;
+    transform.translation += (anchor - cursor_position).extend(0.0);
+}
+
+/// Frames every collider the current demo spawned
+pub fn fit_camera_to_demo(
+    mut controls: ResMut<CameraControls>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    root_query: Query<Entity, With<DemoScoped>>,
+    children_query: Query<&Children>,
+    aabb_query: Query<&ColliderAabb>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<DemoCamera>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    if !action_state.just_pressed(&PlayerAction::FitCameraToDemo) {
+        return;
+    }
+    let Ok((mut transform, mut projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // child colliders (walls and the like) count too
+    let Some((min, max)) = root_query
+        .iter()
+        .flat_map(|root| std::iter::once(root).chain(children_query.iter_descendants(root)))
+        .filter_map(|entity| aabb_query.get(entity).ok())
+        .filter(|aabb| aabb.min.is_finite() && aabb.max.is_finite())
+        .map(|aabb| (aabb.min, aabb.max))
+        .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+    else {
+        return;
+    };
+
+    let size = (max - min) * FIT_MARGIN;
+    controls.mode = CameraMode::Free;
+    transform.translation = ((min + max) / 2.0).extend(transform.translation.z);
+    projection.scale = (size / VIEW_SIZE).max_element().clamp(MIN_ZOOM, MAX_ZOOM);
 }
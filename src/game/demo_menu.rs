@@ -0,0 +1,127 @@
+use super::{
+    bindings::{binds_key, capture_rebind_input, BindingsScreen, PlayerBindings},
+    demo_state::{DemoRegistry, DemoState, EditDemoState},
+    player::{Player, PlayerAction},
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use leafwing_input_manager::prelude::*;
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Number keys jump to the demo registered at the same position, 1 being the first
+const DEMO_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// The demo list overlay and number-key jumps
+pub struct DemoMenuPlugin;
+
+impl Plugin for DemoMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<DemoMenu>()
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    toggle_demo_menu,
+                    // the bindings screen clears `listening` once it has its key
+                    handle_demo_keys.before(capture_rebind_input),
+                    demo_menu_ui,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Default)]
+pub struct DemoMenu {
+    pub open: bool,
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn toggle_demo_menu(
+    mut menu: ResMut<DemoMenu>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    if action_state.just_pressed(&PlayerAction::ToggleDemoMenu) {
+        menu.open = !menu.open;
+    }
+}
+
+pub fn handle_demo_keys(
+    mut contexts: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    registry: Res<DemoRegistry>,
+    bindings_screen: Res<BindingsScreen>,
+    bindings: Res<PlayerBindings>,
+    mut write_edit_demo: EventWriter<EditDemoState>,
+) {
+    // digits typed into a text field or pressed to rebind an action aren't meant for us
+    if contexts.ctx_mut().wants_keyboard_input() || bindings_screen.listening.is_some() {
+        return;
+    }
+    for (key, demo) in DEMO_KEYS.iter().zip(registry.iter()) {
+        // a digit bound to an action belongs to that action
+        if keys.just_pressed(*key) && !binds_key(&bindings.0, *key) {
+            write_edit_demo.send(EditDemoState::JumpTo(demo.state));
+        }
+    }
+}
+
+pub fn demo_menu_ui(
+    mut contexts: EguiContexts,
+    mut menu: ResMut<DemoMenu>,
+    registry: Res<DemoRegistry>,
+    demo_state: Res<State<DemoState>>,
+    mut write_edit_demo: EventWriter<EditDemoState>,
+) {
+    if !menu.open {
+        return;
+    }
+
+    let current = *demo_state.get();
+    egui::Window::new("Demos")
+        .open(&mut menu.open)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("demo_menu_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (index, demo) in registry.iter().enumerate() {
+                        let key = if index < DEMO_KEYS.len() {
+                            format!("{}", index + 1)
+                        } else {
+                            String::new()
+                        };
+                        ui.label(key);
+                        if ui
+                            .selectable_label(demo.state == current, demo.name)
+                            .clicked()
+                        {
+                            write_edit_demo.send(EditDemoState::JumpTo(demo.state));
+                        }
+                        ui.label(demo.description);
+                        ui.end_row();
+                    }
+                });
+        });
+}
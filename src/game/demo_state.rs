@@ -1,8 +1,18 @@
-use bevy::prelude::*;
+use super::player;
+
+use bevy::{
+    ecs::{event::ManualEventReader, schedule::SystemConfigs},
+    prelude::*,
+};
+use bevy_xpbd_2d::{math::Vector, prelude::*};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
 // states ----------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[derive(
+    States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default, Serialize, Deserialize, ValueEnum,
+)]
 pub enum DemoState {
     #[default]
     Home,
@@ -13,26 +23,253 @@ pub enum DemoState {
     Destructible,
 }
 
+// demos -----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Everything the lab needs to know about a demo. Register with `App::add_demo`,
+/// demos are cycled through in the order they were registered.
+/// Entities the demo spawns should be tagged `DemoScoped(Self::STATE)`.
+pub trait Demo: Send + Sync + 'static {
+    const STATE: DemoState;
+    const NAME: &'static str;
+    /// One line shown next to the name in the demo menu
+    const DESCRIPTION: &'static str = "";
+    /// Level file under `assets/` spawned alongside `spawn_systems`, once it has loaded
+    const LEVEL: Option<&'static str> = None;
+    /// World rect the following camera's view is kept inside, `None` for unbounded
+    const CAMERA_BOUNDS: Option<Rect> = None;
+
+    /// Run when the demo is entered, after the player has been spawned
+    fn spawn_systems() -> SystemConfigs;
+
+    /// Run every frame in `Update` while the demo is active
+    fn update_systems() -> Option<SystemConfigs> {
+        None
+    }
+
+    /// Run every physics step while the demo is active, before the broad phase. Anything timed
+    /// belongs here so that it follows pausing, single steps and the time scale.
+    fn physics_systems() -> Option<SystemConfigs> {
+        None
+    }
+
+    /// Run when the demo is exited, after its entities are despawned and world state restored
+    fn exit_systems() -> Option<SystemConfigs> {
+        None
+    }
+
+    /// Any other registration the demo needs (events, collision hooks, etc.)
+    fn build(_app: &mut App) {}
+}
+
+#[derive(SystemSet, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DemoSystems {
+    /// OnEnter: capture world state, OnExit: despawn `DemoScoped` entities and restore world state
+    Scope,
+    Player,
+    Spawn,
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Despawns the entity (recursively) when the given demo is exited
+#[derive(Component, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DemoScoped(pub DemoState);
+
+pub struct DemoInfo {
+    pub state: DemoState,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub level: Option<&'static str>,
+    pub camera_bounds: Option<Rect>,
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Default)]
+pub struct DemoRegistry {
+    demos: Vec<DemoInfo>,
+}
+
+impl DemoRegistry {
+    pub fn iter(&self) -> impl Iterator<Item = &DemoInfo> {
+        self.demos.iter()
+    }
+
+    pub fn get(&self, state: DemoState) -> Option<&DemoInfo> {
+        self.demos.iter().find(|demo| demo.state == state)
+    }
+
+    /// The demo registered after `state`, wrapping around to the first
+    pub fn next(&self, state: DemoState) -> Option<DemoState> {
+        let index = self.demos.iter().position(|demo| demo.state == state)?;
+        Some(self.demos[(index + 1) % self.demos.len()].state)
+    }
+
+    /// The demo registered before `state`, wrapping around to the last
+    pub fn previous(&self, state: DemoState) -> Option<DemoState> {
+        let index = self.demos.iter().position(|demo| demo.state == state)?;
+        let len = self.demos.len();
+        Some(self.demos[(index + len - 1) % len].state)
+    }
+}
+
+/// Global state demos are free to modify, captured on entering a demo and put back on exit.
+/// Player components need no snapshot: the player is `DemoScoped` and respawned per demo.
+/// Lab settings that overlap it (the gravity factor, the time scale) are written into the
+/// snapshot as well as the world when changed, so exiting a demo doesn't undo them.
+#[derive(Resource, Clone, Debug)]
+pub struct DemoWorldSnapshot {
+    pub gravity: Vector,
+    pub physics_speed: f32,
+}
+
+/// Incremented every time a demo is entered, including restarts. Anything holding on to
+/// entities across frames can compare generations to notice its entities went stale.
+#[derive(Resource, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DemoGeneration(pub u32);
+
+pub trait DemoAppExt {
+    fn add_demo<D: Demo>(&mut self) -> &mut Self;
+}
+
+impl DemoAppExt for App {
+    fn add_demo<D: Demo>(&mut self) -> &mut Self {
+        self.init_resource::<DemoRegistry>()
+            .init_resource::<DemoGeneration>();
+        let mut registry = self.world.resource_mut::<DemoRegistry>();
+        assert!(
+            registry.get(D::STATE).is_none(),
+            "{:?} was registered as a demo more than once",
+            D::STATE
+        );
+        registry.demos.push(DemoInfo {
+            state: D::STATE,
+            name: D::NAME,
+            description: D::DESCRIPTION,
+            level: D::LEVEL,
+            camera_bounds: D::CAMERA_BOUNDS,
+        });
+
+        self.configure_sets(
+            OnEnter(D::STATE),
+            (DemoSystems::Scope, DemoSystems::Player, DemoSystems::Spawn).chain(),
+        )
+        .add_systems(
+            OnEnter(D::STATE),
+            (
+                (snapshot_world_state, advance_demo_generation).in_set(DemoSystems::Scope),
+                player::spawn_player.in_set(DemoSystems::Player),
+                D::spawn_systems().in_set(DemoSystems::Spawn),
+            ),
+        )
+        .add_systems(
+            OnExit(D::STATE),
+            (despawn_demo_scoped(D::STATE), restore_world_state).in_set(DemoSystems::Scope),
+        );
+
+        if let Some(exit_systems) = D::exit_systems() {
+            self.add_systems(OnExit(D::STATE), exit_systems.after(DemoSystems::Scope));
+        }
+        if let Some(update_systems) = D::update_systems() {
+            self.add_systems(Update, update_systems.run_if(in_state(D::STATE)));
+        }
+        if let Some(physics_systems) = D::physics_systems() {
+            self.add_systems(
+                PhysicsSchedule,
+                physics_systems
+                    .run_if(in_state(D::STATE))
+                    .before(PhysicsStepSet::BroadPhase),
+            );
+        }
+
+        D::build(self);
+        self
+    }
+}
+
 // events ----------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Event)]
-pub struct EditDemoState;
+#[derive(Event, Clone, Copy, Debug)]
+pub enum EditDemoState {
+    Next,
+    Previous,
+    JumpTo(DemoState),
+    /// Runs the demo's `OnExit` and `OnEnter` again
+    Restart,
+}
 
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
 pub fn handle_edit_demo_state(
-    current_demo_state: Res<State<DemoState>>,
-    mut next_demo_state: ResMut<NextState<DemoState>>,
-    mut read_edit_demo_state: EventReader<EditDemoState>,
+    world: &mut World,
+    mut reader: Local<ManualEventReader<EditDemoState>>,
 ) {
-    for _ in read_edit_demo_state.read() {
-        match *current_demo_state.get() {
-            DemoState::Home => next_demo_state.set(DemoState::Colliders),
-            DemoState::Colliders => next_demo_state.set(DemoState::ConveyorBelt),
-            DemoState::ConveyorBelt => next_demo_state.set(DemoState::Magnet),
-            DemoState::Magnet => next_demo_state.set(DemoState::Joint),
-            DemoState::Joint => next_demo_state.set(DemoState::Destructible),
-            DemoState::Destructible => next_demo_state.set(DemoState::Home),
+    let edits: Vec<EditDemoState> = reader
+        .read(world.resource::<Events<EditDemoState>>())
+        .copied()
+        .collect();
+
+    for edit in edits {
+        let current = *world.resource::<State<DemoState>>().get();
+        let registry = world.resource::<DemoRegistry>();
+        let target = match edit {
+            EditDemoState::Next => registry.next(current),
+            EditDemoState::Previous => registry.previous(current),
+            EditDemoState::JumpTo(demo) => registry.get(demo).map(|demo| demo.state),
+            EditDemoState::Restart => Some(current),
+        };
+
+        match target {
+            // re-entering the running demo has to bypass `NextState`
+            Some(target) if target == current => enter_demo_now(world, current),
+            Some(target) => world.resource_mut::<NextState<DemoState>>().set(target),
+            None => {}
+        }
+    }
+}
+
+pub fn despawn_demo_scoped(state: DemoState) -> impl FnMut(Commands, Query<(Entity, &DemoScoped)>) {
+    move |mut commands: Commands, scoped_query: Query<(Entity, &DemoScoped)>| {
+        for (scoped_entity, scope) in scoped_query.iter() {
+            if scope.0 == state {
+                commands.entity(scoped_entity).despawn_recursive();
+            }
         }
     }
 }
+
+pub fn snapshot_world_state(
+    mut commands: Commands,
+    gravity: Res<Gravity>,
+    physics_time: Res<Time<Physics>>,
+) {
+    commands.insert_resource(DemoWorldSnapshot {
+        gravity: gravity.0,
+        physics_speed: physics_time.relative_speed(),
+    });
+}
+
+pub fn advance_demo_generation(mut generation: ResMut<DemoGeneration>) {
+    generation.0 = generation.0.wrapping_add(1);
+}
+
+pub fn restore_world_state(
+    snapshot: Option<Res<DemoWorldSnapshot>>,
+    mut gravity: ResMut<Gravity>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    gravity.0 = snapshot.gravity;
+    physics_time.set_relative_speed(snapshot.physics_speed);
+}
+
+/// Exits the running demo and enters `demo` right away, re-entering it if it is already running.
+/// Unlike `NextState`, this takes effect before the next system runs.
+pub fn enter_demo_now(world: &mut World, demo: DemoState) {
+    let current = *world.resource::<State<DemoState>>().get();
+    let _ = world.try_run_schedule(OnExit(current));
+    world.insert_resource(State::new(demo));
+    let _ = world.try_run_schedule(OnEnter(demo));
+}
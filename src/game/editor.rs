@@ -0,0 +1,543 @@
+use super::{
+    camera::WorldCursor,
+    demo_state::{DemoScoped, DemoState},
+    level::{read_level, write_level, Level},
+    player::{Player, PlayerAction},
+    snapshot::{capture_entities, physics_roots, spawn_entities, BodyKind, ShapeSnapshot},
+};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_inspector_egui::{bevy_egui::EguiContext, bevy_inspector, egui};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use std::path::Path;
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub const DEFAULT_EXPORT_PATH: &str = "assets/levels/editor.level.ron";
+
+const SELECTED_COLOR: Color = Color::YELLOW;
+const JOINT_COLOR: Color = Color::FUCHSIA;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Place, move, resize and rotate bodies and join them with the mouse while physics is paused
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<Editor>()
+            .init_resource::<EditorCursor>()
+            // Update ----------------------------------------------------------
+            .add_systems(Update, toggle_editor)
+            .add_systems(
+                Update,
+                (
+                    update_editor_cursor,
+                    handle_editor_clicks,
+                    drag_selected_body,
+                    draw_editor_gizmos,
+                    editor_panel_ui,
+                )
+                    .chain()
+                    .after(toggle_editor)
+                    .run_if(editor_active),
+            );
+    }
+}
+
+// tools -----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShapeKind {
+    Circle,
+    Rectangle,
+    RoundRectangle,
+    Capsule,
+    Triangle,
+    Ellipse,
+    RegularPolygon,
+}
+
+impl ShapeKind {
+    pub const ALL: [Self; 7] = [
+        Self::Circle,
+        Self::Rectangle,
+        Self::RoundRectangle,
+        Self::Capsule,
+        Self::Triangle,
+        Self::Ellipse,
+        Self::RegularPolygon,
+    ];
+
+    /// Roughly the size of the shapes in the colliders demo
+    pub fn default_shape(self) -> ShapeSnapshot {
+        match self {
+            Self::Circle => ShapeSnapshot::Circle { radius: 5.0 },
+            Self::Rectangle => ShapeSnapshot::Rectangle {
+                width: 10.0,
+                height: 10.0,
+            },
+            Self::RoundRectangle => ShapeSnapshot::RoundRectangle {
+                width: 9.0,
+                height: 5.0,
+                border_radius: 1.5,
+            },
+            Self::Capsule => ShapeSnapshot::Capsule {
+                a: Vec2::new(0.0, -2.5),
+                b: Vec2::new(0.0, 2.5),
+                radius: 5.0,
+            },
+            Self::Triangle => ShapeSnapshot::Triangle {
+                a: Vec2::new(-5.0, -5.0),
+                b: Vec2::new(5.0, -5.0),
+                c: Vec2::new(0.0, 5.0),
+            },
+            Self::Ellipse => ShapeSnapshot::Ellipse {
+                half_width: 5.0,
+                half_height: 7.0,
+            },
+            Self::RegularPolygon => ShapeSnapshot::RegularPolygon {
+                circumradius: 6.0,
+                sides: 9,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EditorJointKind {
+    Prismatic,
+    Revolute,
+    Distance,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub enum EditorTool {
+    /// Click to select, drag to move, shift-drag to resize, ctrl-drag to rotate
+    #[default]
+    Select,
+    Place(ShapeKind),
+    /// Click two bodies to join them
+    Joint(EditorJointKind),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub enum DragMode {
+    #[default]
+    Move,
+    Resize,
+    Rotate,
+}
+
+pub struct EditorDrag {
+    pub mode: DragMode,
+    pub start_cursor: Vec2,
+    pub start_position: Vec2,
+    pub start_rotation: f32,
+    /// the body's own collider when the drag started, resizing always scales from this
+    pub start_shape: Option<ShapeSnapshot>,
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource)]
+pub struct Editor {
+    pub active: bool,
+    pub tool: EditorTool,
+    /// what `EditorTool::Place` spawns
+    pub body_kind: BodyKind,
+    pub selected: Option<Entity>,
+    /// first body picked with `EditorTool::Joint`
+    pub joint_start: Option<Entity>,
+    pub drag: Option<EditorDrag>,
+    pub export_path: String,
+    /// physics pause state to put back when the editor closes
+    was_paused: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            active: false,
+            tool: EditorTool::default(),
+            body_kind: BodyKind::Dynamic,
+            selected: None,
+            joint_start: None,
+            drag: None,
+            export_path: DEFAULT_EXPORT_PATH.to_owned(),
+            was_paused: false,
+        }
+    }
+}
+
+/// Mouse state for this frame, ignoring anything egui is using
+#[derive(Resource, Default)]
+pub struct EditorCursor {
+    pub position: Option<Vec2>,
+    pub just_pressed: bool,
+    pub pressed: bool,
+    /// what dragging would do given the modifier keys held
+    pub drag_mode: DragMode,
+}
+
+// run conditions --------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn editor_active(editor: Res<Editor>) -> bool {
+    editor.active
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Joints of type `J` attached to `body`. Joints are usually children of their second body,
+/// so despawning the first one doesn't take them along.
+fn joints_attached_to<J: Joint + Component>(world: &mut World, body: Entity) -> Vec<Entity> {
+    world
+        .query::<(Entity, &J)>()
+        .iter(world)
+        .filter(|(_, joint)| joint.entities().contains(&body))
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn toggle_editor(
+    mut editor: ResMut<Editor>,
+    mut physics_time: ResMut<Time<Physics>>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    if !action_state.just_pressed(&PlayerAction::ToggleEditor) {
+        return;
+    }
+
+    if editor.active {
+        if !editor.was_paused {
+            physics_time.unpause();
+        }
+        let export_path = std::mem::take(&mut editor.export_path);
+        *editor = Editor {
+            export_path,
+            ..default()
+        };
+    } else {
+        editor.active = true;
+        editor.was_paused = physics_time.is_paused();
+        physics_time.pause();
+    }
+}
+
+pub fn update_editor_cursor(
+    mut cursor: ResMut<EditorCursor>,
+    world_cursor: Res<WorldCursor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    cursor.position = world_cursor.position;
+    cursor.just_pressed = mouse.just_pressed(MouseButton::Left) && !world_cursor.over_ui;
+    cursor.pressed = mouse.pressed(MouseButton::Left);
+    cursor.drag_mode = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        DragMode::Resize
+    } else if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        DragMode::Rotate
+    } else {
+        DragMode::Move
+    };
+}
+
+pub fn handle_editor_clicks(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    cursor: Res<EditorCursor>,
+    mut spatial_query: SpatialQuery,
+    demo_state: Res<State<DemoState>>,
+    collider_query: Query<&ColliderParent>,
+    body_query: Query<(&Position, &Rotation, Option<&Collider>), With<RigidBody>>,
+) {
+    let Some(cursor_position) = cursor.position.filter(|_| cursor.just_pressed) else {
+        return;
+    };
+
+    // physics is paused while editing, so the pipeline misses bodies placed or moved since
+    spatial_query.update_pipeline();
+
+    // the body owning the first collider under the cursor
+    let picked = spatial_query
+        .point_intersections(cursor_position, SpatialQueryFilter::default())
+        .into_iter()
+        .filter_map(|collider| collider_query.get(collider).ok())
+        .map(ColliderParent::get)
+        .find(|body| body_query.contains(*body));
+
+    match editor.tool {
+        EditorTool::Select => {
+            editor.selected = picked;
+            editor.drag = picked.and_then(|body| {
+                let (position, rotation, collider) = body_query.get(body).ok()?;
+                Some(EditorDrag {
+                    mode: cursor.drag_mode,
+                    start_cursor: cursor_position,
+                    start_position: position.0,
+                    start_rotation: rotation.as_radians(),
+                    start_shape: collider.and_then(ShapeSnapshot::from_collider),
+                })
+            });
+        }
+        EditorTool::Place(shape_kind) => {
+            let Some(collider) = shape_kind.default_shape().to_collider() else {
+                return;
+            };
+            let body = commands
+                .spawn((
+                    Name::new(format!("Editor{shape_kind:?}")),
+                    DemoScoped(*demo_state.get()),
+                    editor.body_kind.to_rigid_body(),
+                    collider,
+                    TransformBundle::from_transform(Transform::from_translation(
+                        cursor_position.extend(0.0),
+                    )),
+                ))
+                .id();
+            editor.selected = Some(body);
+        }
+        EditorTool::Joint(joint_kind) => {
+            let Some(body2) = picked else {
+                editor.joint_start = None;
+                return;
+            };
+            let Some(body1) = editor.joint_start.take().filter(|body1| *body1 != body2) else {
+                editor.joint_start = Some(body2);
+                return;
+            };
+            let (Ok((position1, rotation1, _)), Ok((position2, rotation2, _))) =
+                (body_query.get(body1), body_query.get(body2))
+            else {
+                return;
+            };
+
+            // anchor both bodies at their current midpoint so the joint starts out satisfied
+            let to_local = |rotation: &Rotation, offset: Vec2| {
+                (Quat::from_rotation_z(-rotation.as_radians()) * offset.extend(0.0)).xy()
+            };
+            let midpoint = (position1.0 + position2.0) / 2.0;
+            let anchor1 = to_local(rotation1, midpoint - position1.0);
+            let anchor2 = to_local(rotation2, midpoint - position2.0);
+            let offset = position2.0 - position1.0;
+
+            // joints are children of the second body, same as the demos
+            commands.entity(body2).with_children(|children| {
+                match joint_kind {
+                    EditorJointKind::Prismatic => children.spawn(
+                        PrismaticJoint::new(body1, body2)
+                            .with_local_anchor_1(anchor1)
+                            .with_local_anchor_2(anchor2)
+                            .with_free_axis(to_local(rotation1, offset.normalize_or_zero())),
+                    ),
+                    EditorJointKind::Revolute => children.spawn(
+                        RevoluteJoint::new(body1, body2)
+                            .with_local_anchor_1(anchor1)
+                            .with_local_anchor_2(anchor2),
+                    ),
+                    EditorJointKind::Distance => children
+                        .spawn(DistanceJoint::new(body1, body2).with_rest_length(offset.length())),
+                };
+            });
+            editor.selected = Some(body2);
+        }
+    }
+}
+
+pub fn drag_selected_body(
+    mut editor: ResMut<Editor>,
+    cursor: Res<EditorCursor>,
+    mut body_query: Query<(
+        &mut Position,
+        &mut Rotation,
+        &mut Transform,
+        Option<&mut Collider>,
+    )>,
+) {
+    if !cursor.pressed {
+        editor.drag = None;
+        return;
+    }
+    let (Some(drag), Some(selected), Some(cursor_position)) =
+        (&editor.drag, editor.selected, cursor.position)
+    else {
+        return;
+    };
+    let Ok((mut position, mut rotation, mut transform, collider)) = body_query.get_mut(selected)
+    else {
+        return;
+    };
+
+    match drag.mode {
+        DragMode::Move => {
+            position.0 = drag.start_position + cursor_position - drag.start_cursor;
+            transform.translation = position.0.extend(transform.translation.z);
+        }
+        DragMode::Rotate => {
+            let angle = |point: Vec2| {
+                let offset = point - drag.start_position;
+                offset.y.atan2(offset.x)
+            };
+            let radians = drag.start_rotation + angle(cursor_position) - angle(drag.start_cursor);
+            *rotation = Rotation::from_radians(radians);
+            transform.rotation = Quat::from_rotation_z(radians);
+        }
+        DragMode::Resize => {
+            let (Some(start_shape), Some(mut collider)) = (&drag.start_shape, collider) else {
+                return;
+            };
+            let start_distance = drag.start_cursor.distance(drag.start_position).max(1.0);
+            let factor = (cursor_position.distance(drag.start_position) / start_distance).max(0.1);
+            if let Some(resized) = start_shape.scaled(factor).to_collider() {
+                *collider = resized;
+            }
+        }
+    }
+}
+
+pub fn draw_editor_gizmos(
+    mut gizmos: Gizmos,
+    editor: Res<Editor>,
+    cursor: Res<EditorCursor>,
+    body_query: Query<&Position>,
+) {
+    if let Some(position) = editor.selected.and_then(|body| body_query.get(body).ok()) {
+        gizmos.circle_2d(position.0, 2.0, SELECTED_COLOR);
+    }
+    if let (Some(position), Some(cursor_position)) = (
+        editor
+            .joint_start
+            .and_then(|body| body_query.get(body).ok()),
+        cursor.position,
+    ) {
+        gizmos.line_2d(position.0, cursor_position, JOINT_COLOR);
+    }
+}
+
+pub fn editor_panel_ui(world: &mut World) {
+    let Ok(egui_context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+
+    let mut delete_selected = false;
+    let mut export = false;
+    let mut import = false;
+
+    world.resource_scope(|world, mut editor: Mut<Editor>| {
+        egui::Window::new("Editor").show(egui_context.get_mut(), |ui| {
+            ui.label("drag: move, shift-drag: resize, ctrl-drag: rotate");
+
+            ui.selectable_value(&mut editor.tool, EditorTool::Select, "Select");
+            ui.label("Place");
+            ui.horizontal_wrapped(|ui| {
+                for shape_kind in ShapeKind::ALL {
+                    ui.selectable_value(
+                        &mut editor.tool,
+                        EditorTool::Place(shape_kind),
+                        format!("{shape_kind:?}"),
+                    );
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut editor.body_kind, BodyKind::Dynamic, "Dynamic");
+                ui.radio_value(&mut editor.body_kind, BodyKind::Static, "Static");
+            });
+            ui.label("Joint");
+            ui.horizontal_wrapped(|ui| {
+                for joint_kind in [
+                    EditorJointKind::Prismatic,
+                    EditorJointKind::Revolute,
+                    EditorJointKind::Distance,
+                ] {
+                    ui.selectable_value(
+                        &mut editor.tool,
+                        EditorTool::Joint(joint_kind),
+                        format!("{joint_kind:?}"),
+                    );
+                }
+            });
+
+            ui.separator();
+            match editor
+                .selected
+                .filter(|entity| world.get_entity(*entity).is_some())
+            {
+                Some(selected) => {
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| bevy_inspector::ui_for_entity(world, selected, ui));
+                    delete_selected = ui.button("Delete").clicked();
+                }
+                None => {
+                    ui.label("nothing selected");
+                }
+            }
+
+            ui.separator();
+            ui.text_edit_singleline(&mut editor.export_path);
+            ui.horizontal(|ui| {
+                export = ui.button("Export").clicked();
+                import = ui.button("Import").clicked();
+            });
+        });
+    });
+
+    let mut editor = world.resource_mut::<Editor>();
+    let path = editor.export_path.clone();
+    let deleted = if delete_selected {
+        editor.selected.take()
+    } else {
+        None
+    };
+
+    if let Some(deleted) = deleted {
+        let joints: Vec<Entity> = [
+            joints_attached_to::<FixedJoint>(world, deleted),
+            joints_attached_to::<DistanceJoint>(world, deleted),
+            joints_attached_to::<PrismaticJoint>(world, deleted),
+            joints_attached_to::<RevoluteJoint>(world, deleted),
+            joints_attached_to::<SphericalJoint>(world, deleted),
+        ]
+        .concat();
+        world.entity_mut(deleted).despawn_recursive();
+        // some of them may have gone with the body already
+        for joint in joints {
+            if let Some(joint) = world.get_entity_mut(joint) {
+                joint.despawn_recursive();
+            }
+        }
+    }
+    if export {
+        let roots = physics_roots(world);
+        let level = Level {
+            entities: capture_entities(world, &roots),
+        };
+        match write_level(&level, Path::new(&path)) {
+            Ok(()) => info!("exported {} entities to {path}", level.entities.len()),
+            Err(err) => error!("failed to export level to {path}: {err}"),
+        }
+    }
+    if import {
+        match read_level(Path::new(&path)) {
+            Ok(level) => {
+                let demo = *world.resource::<State<DemoState>>().get();
+                spawn_entities(world, &level.entities, DemoScoped(demo));
+                info!("imported {} entities from {path}", level.entities.len());
+            }
+            Err(err) => error!("failed to import level from {path}: {err}"),
+        }
+    }
+}
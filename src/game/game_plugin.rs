@@ -1,4 +1,11 @@
-use super::{camera, demo_state::*, physics_demos::demo_plugin, player};
+use super::{
+    bindings, camera, demo_menu,
+    demo_state::*,
+    editor, kinematic_platform, level, mouse_drag, one_way_platform,
+    physics_demos::demo_plugin,
+    platformer, player, replay, rewind, snapshot, time_controls,
+    tuning::{self, TuningAppExt},
+};
 
 use bevy::{prelude::*, transform::TransformSystem};
 use bevy_xpbd_2d::prelude::*;
@@ -6,74 +13,156 @@ use leafwing_input_manager::prelude::*;
 
 // plugins ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-pub struct GamePlugin;
+/// The full lab: simulation plus camera and debug rendering
+#[derive(Default)]
+pub struct GamePlugin {
+    pub starting_demo: DemoState,
+}
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app
             // plugins ---------------------------------------------------------
             .add_plugins((
-                PhysicsPlugins::default(),
+                SimulationPlugin {
+                    starting_demo: self.starting_demo,
+                },
                 PhysicsDebugPlugin::default(),
-                InputManagerPlugin::<player::PlayerAction>::default(),
-                demo_plugin::DemoPlugin,
+                tuning::TuningPanelPlugin,
+                editor::EditorPlugin,
+                mouse_drag::MouseDragPlugin,
+                bindings::BindingsPlugin,
+                demo_menu::DemoMenuPlugin,
             ))
             // resources -------------------------------------------------------
-            .insert_resource(GravityFactor { factor: 100.0 })
-            // states ----------------------------------------------------------
-            .init_state::<DemoState>()
+            .init_resource::<camera::WorldCursor>()
+            .init_resource::<camera::CameraControls>()
+            // tuning ----------------------------------------------------------
+            .add_tuning::<camera::CameraFollowSettings>(None)
             // events ----------------------------------------------------------
-            .add_event::<EditDemoState>()
             .add_event::<camera::CameraTranslation>()
             // Startup ---------------------------------------------------------
-            .add_systems(Startup, camera::spawn_camera)
-            // PostStartup -----------------------------------------------------
-            .add_systems(PostStartup, setup_gravity)
-            // Entering DemoState:: --------------------------------------------
-            .add_systems(OnEnter(DemoState::Home), player::spawn_player)
-            .add_systems(OnEnter(DemoState::Colliders), player::spawn_player)
-            .add_systems(OnEnter(DemoState::ConveyorBelt), player::spawn_player)
-            .add_systems(OnEnter(DemoState::Magnet), player::spawn_player)
-            .add_systems(OnEnter(DemoState::Joint), player::spawn_player)
-            .add_systems(OnEnter(DemoState::Destructible), player::spawn_player)
-            // Exiting DemoState:: ---------------------------------------------
-            .add_systems(OnExit(DemoState::Home), player::despawn_player)
             .add_systems(
-                OnExit(DemoState::Colliders),
-                (player::despawn_player, setup_gravity),
+                Startup,
+                (
+                    camera::spawn_camera,
+                    time_controls::spawn_time_indicator,
+                    rewind::spawn_rewind_indicator,
+                ),
             )
-            .add_systems(OnExit(DemoState::ConveyorBelt), player::despawn_player)
-            .add_systems(OnExit(DemoState::Magnet), player::despawn_player)
-            .add_systems(OnExit(DemoState::Joint), player::despawn_player)
-            .add_systems(OnExit(DemoState::Destructible), player::despawn_player)
+            // PreUpdate -------------------------------------------------------
+            .add_systems(PreUpdate, camera::update_world_cursor)
             // Update ----------------------------------------------------------
             .add_systems(
                 Update,
                 (
-                    handle_edit_demo_state,
-                    player::handle_player_input,
-                    player::pass_through_one_way_platform,
+                    time_controls::update_time_indicator,
+                    rewind::update_rewind_indicator,
+                    camera::reset_camera_for_demo.run_if(resource_changed::<DemoGeneration>),
+                    camera::toggle_camera_mode,
+                    camera::handle_camera_translations,
+                    camera::zoom_camera,
+                    camera::pan_camera,
+                    camera::fit_camera_to_demo,
+                    kinematic_platform::draw_platform_paths,
                 ),
             )
             // PostUpdate ------------------------------------------------------
             .add_systems(
                 PostUpdate,
                 camera::camera_follow_player
+                    .run_if(camera::camera_follows_target)
                     .after(PhysicsSet::Sync)
                     .before(TransformSystem::TransformPropagate),
             );
     }
 }
 
+/// Physics, demos and the player, without anything that needs a window or GPU
+#[derive(Default)]
+pub struct SimulationPlugin {
+    pub starting_demo: DemoState,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // plugins ---------------------------------------------------------
+            .add_plugins((
+                PhysicsPlugins::default(),
+                InputManagerPlugin::<player::PlayerAction>::default(),
+                demo_plugin::DemoPlugin,
+                snapshot::SnapshotPlugin,
+                replay::ReplayPlugin,
+                time_controls::TimeControlsPlugin,
+                rewind::RewindPlugin,
+                level::LevelPlugin,
+                one_way_platform::OneWayPlatformPlugin,
+                kinematic_platform::KinematicPlatformPlugin,
+                platformer::PlatformerPlugin,
+            ))
+            // resources -------------------------------------------------------
+            .init_resource::<bindings::PlayerBindings>()
+            // tuning ----------------------------------------------------------
+            .add_tuning::<GravityFactor>(None)
+            .add_tuning::<player::PlayerParams>(None)
+            // states ----------------------------------------------------------
+            .insert_state(self.starting_demo)
+            // events ----------------------------------------------------------
+            .add_event::<EditDemoState>()
+            // PostStartup -----------------------------------------------------
+            .add_systems(PostStartup, setup_gravity)
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    handle_edit_demo_state,
+                    apply_gravity_factor.run_if(resource_changed::<GravityFactor>),
+                    player::handle_player_input,
+                    player::handle_grab,
+                    player::pass_through_one_way_platform,
+                ),
+            )
+            // PhysicsSchedule -------------------------------------------------
+            .add_systems(
+                PhysicsSchedule,
+                player::drone_thrust
+                    .run_if(resource_equals(player::PlayerControllerMode::Drone))
+                    .before(PhysicsStepSet::BroadPhase),
+            );
+    }
+}
+
 // resources -------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Resource)]
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
 pub struct GravityFactor {
     pub factor: f32,
 }
 
+impl Default for GravityFactor {
+    fn default() -> Self {
+        Self { factor: 100.0 }
+    }
+}
+
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-fn setup_gravity(mut gravity: ResMut<Gravity>, g_factor: Res<GravityFactor>) {
+pub fn setup_gravity(mut gravity: ResMut<Gravity>, g_factor: Res<GravityFactor>) {
     gravity.0 = Vec2::NEG_Y * g_factor.factor;
 }
+
+/// Keeps the gravity direction (demos may rotate it) and rescales it to the tuned factor
+pub fn apply_gravity_factor(
+    mut gravity: ResMut<Gravity>,
+    g_factor: Res<GravityFactor>,
+    world_snapshot: Option<ResMut<DemoWorldSnapshot>>,
+) {
+    let rescale = |gravity: Vec2| gravity.try_normalize().unwrap_or(Vec2::NEG_Y) * g_factor.factor;
+    gravity.0 = rescale(gravity.0);
+
+    if let Some(mut world_snapshot) = world_snapshot {
+        world_snapshot.gravity = rescale(world_snapshot.gravity);
+    }
+}
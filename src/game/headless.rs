@@ -0,0 +1,90 @@
+use super::{
+    demo_state::{enter_demo_now, DemoState},
+    game_plugin::SimulationPlugin,
+    level::DemoLevels,
+};
+
+use bevy::{
+    hierarchy::HierarchyPlugin,
+    input::InputPlugin,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+    utils::{Duration, Instant},
+};
+use bevy_xpbd_2d::prelude::*;
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Default tick rate, every `HeadlessLab::run_ticks` tick advances both `Time` and
+/// `Time<Physics>` by one tick
+pub const HEADLESS_TICK_HZ: f64 = 60.0;
+
+/// How long to wait for level files before starting anyway
+const LEVEL_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+// headless app ----------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// The lab without a window, renderer or real-time clock, for tests and scripted runs.
+/// Each tick is one `App::update` that steps physics exactly once.
+pub struct HeadlessLab {
+    pub app: App,
+    pub tick_hz: f64,
+}
+
+impl HeadlessLab {
+    pub fn new(demo: DemoState) -> Self {
+        Self::with_tick_hz(demo, HEADLESS_TICK_HZ)
+    }
+
+    pub fn with_tick_hz(demo: DemoState, tick_hz: f64) -> Self {
+        let mut app = App::new();
+        let tick = Duration::from_secs_f64(1.0 / tick_hz);
+
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+            AssetPlugin::default(),
+            SimulationPlugin {
+                starting_demo: demo,
+            },
+        ))
+        // advance the clock by a fixed amount per update instead of wall time
+        .insert_resource(TimeUpdateStrategy::ManualDuration(tick))
+        .insert_resource(Time::new_with(Physics::fixed_once_hz(tick_hz)));
+
+        // run startup and enter the starting demo
+        app.update();
+
+        // levels load in the background, wait for them and restart the demo so that
+        // every run starts from the same state at tick 0
+        let started = Instant::now();
+        while !app
+            .world
+            .resource::<DemoLevels>()
+            .all_settled(app.world.resource::<AssetServer>())
+        {
+            if started.elapsed() > LEVEL_LOAD_TIMEOUT {
+                warn!("timed out waiting for levels to load");
+                break;
+            }
+            app.update();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        enter_demo_now(&mut app.world, demo);
+
+        Self { app, tick_hz }
+    }
+
+    pub fn run_ticks(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+}
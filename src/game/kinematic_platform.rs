@@ -0,0 +1,574 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_2d::{math::Vector, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts::{PI, TAU};
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+const PATH_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.25);
+const STOP_RADIUS: f32 = 1.5;
+/// degrees a contact normal may lean away from straight up and still count as resting on a
+/// platform
+const MAX_RIDE_ANGLE: f32 = 50.0;
+/// physics steps a rider is remembered after losing contact, so small bounces and contact gaps
+/// don't count as stepping on again
+const RIDER_GRACE_STEPS: u32 = 10;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Moves `KinematicPlatform`s along their paths and carries the bodies riding them. Path
+/// gizmos are drawn separately by `draw_platform_paths`, which needs a renderer.
+pub struct KinematicPlatformPlugin;
+
+impl Plugin for KinematicPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // PhysicsSchedule -------------------------------------------------
+            // steps in lockstep with physics, so pausing and time scale apply
+            .add_systems(
+                PhysicsSchedule,
+                (move_kinematic_platforms, carry_platform_riders)
+                    .chain()
+                    .before(PhysicsStepSet::BroadPhase),
+            );
+    }
+}
+
+// path description ------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PathMode {
+    /// Back to the first waypoint after the last
+    #[default]
+    Loop,
+    /// Back and forth along the waypoints
+    PingPong,
+    /// Stops for good at the last waypoint
+    Once,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps `t` in 0..=1 to eased progress in 0..=1
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Waypoint {
+    /// world-space position
+    pub translation: Vec2,
+    /// radians, not wrapped, so several turns between two waypoints are possible
+    #[serde(default)]
+    pub rotation: f32,
+    /// seconds to get here from the previous waypoint (or back from the next one when
+    /// ping-ponging), ignored on the first waypoint unless looping
+    #[serde(default)]
+    pub travel_time: f32,
+    /// seconds to stay here once arrived
+    #[serde(default)]
+    pub wait: f32,
+    /// easing of the travel to here
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl Waypoint {
+    pub fn new(translation: Vec2, travel_time: f32) -> Self {
+        Self {
+            translation,
+            rotation: 0.0,
+            travel_time,
+            wait: 0.0,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_wait(mut self, wait: f32) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+/// Where a platform is along its path
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PathProgress {
+    pub from: usize,
+    pub to: usize,
+    /// travelling from a later waypoint to an earlier one while ping-ponging
+    pub reverse: bool,
+    /// seconds into the current leg, travel then wait
+    pub elapsed: f32,
+    pub finished: bool,
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// A kinematic body following waypoints. It is driven by velocity rather than by setting its
+/// position, so bodies resting on it can be carried along. Spawn it at its first waypoint.
+///
+/// There is always at least one waypoint and the progress always indexes into them, which
+/// `new` and deserialization both make sure of.
+#[derive(Component, Clone, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedKinematicPlatform")]
+pub struct KinematicPlatform {
+    waypoints: Vec<Waypoint>,
+    mode: PathMode,
+    progress: PathProgress,
+}
+
+/// A `KinematicPlatform` as read from a level or snapshot, before it's checked
+#[derive(Deserialize)]
+struct UncheckedKinematicPlatform {
+    waypoints: Vec<Waypoint>,
+    #[serde(default)]
+    mode: PathMode,
+    #[serde(default)]
+    progress: PathProgress,
+}
+
+impl TryFrom<UncheckedKinematicPlatform> for KinematicPlatform {
+    type Error = String;
+
+    fn try_from(unchecked: UncheckedKinematicPlatform) -> Result<Self, Self::Error> {
+        let mut waypoints = unchecked.waypoints.into_iter();
+        let first = waypoints
+            .next()
+            .ok_or_else(|| "a kinematic platform needs at least one waypoint".to_string())?;
+        let mut platform = Self::new(unchecked.mode, first, waypoints);
+
+        // a single waypoint is never left, so there is no progress to keep
+        let count = platform.waypoints.len();
+        if count > 1 {
+            let progress = unchecked.progress;
+            if progress.from >= count || progress.to >= count || !progress.elapsed.is_finite() {
+                return Err(format!(
+                    "path progress from {} to {} after {}s doesn't fit {count} waypoints",
+                    progress.from, progress.to, progress.elapsed
+                ));
+            }
+            platform.progress = progress;
+        }
+        Ok(platform)
+    }
+}
+
+impl KinematicPlatform {
+    /// Starts at `first`, heading for the first of `rest`
+    pub fn new(mode: PathMode, first: Waypoint, rest: impl IntoIterator<Item = Waypoint>) -> Self {
+        let waypoints: Vec<Waypoint> = std::iter::once(first).chain(rest).collect();
+        let progress = PathProgress {
+            to: usize::from(waypoints.len() > 1),
+            ..default()
+        };
+        Self {
+            waypoints,
+            mode,
+            progress,
+        }
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    pub fn mode(&self) -> PathMode {
+        self.mode
+    }
+
+    pub fn progress(&self) -> &PathProgress {
+        &self.progress
+    }
+
+    /// The waypoint after `index` when heading in the given direction, with the new direction
+    fn next_stop(&self, index: usize, reverse: bool) -> Option<(usize, bool)> {
+        let last = self.waypoints.len() - 1;
+        match self.mode {
+            PathMode::Loop => Some(((index + 1) % self.waypoints.len(), false)),
+            PathMode::Once => (index < last).then_some((index + 1, false)),
+            PathMode::PingPong => match (reverse, index) {
+                (false, index) if index < last => Some((index + 1, false)),
+                (false, index) => Some((index - 1, true)),
+                (true, 0) => Some((1, false)),
+                (true, index) => Some((index - 1, true)),
+            },
+        }
+    }
+
+    /// The waypoint whose `travel_time` and `easing` describe the current leg
+    fn leg(&self) -> &Waypoint {
+        let progress = &self.progress;
+        let index = if progress.reverse {
+            progress.from
+        } else {
+            progress.to
+        };
+        &self.waypoints[index]
+    }
+
+    /// Position and rotation on the path right now
+    pub fn pose(&self) -> (Vec2, f32) {
+        let progress = &self.progress;
+        let from = &self.waypoints[progress.from];
+        if self.waypoints.len() < 2 || progress.finished {
+            return (from.translation, from.rotation);
+        }
+
+        let to = &self.waypoints[progress.to];
+        let leg = self.leg();
+        let t = if leg.travel_time > 0.0 {
+            progress.elapsed / leg.travel_time
+        } else {
+            1.0
+        };
+        let t = leg.easing.apply(t);
+        (
+            from.translation.lerp(to.translation, t),
+            from.rotation + (to.rotation - from.rotation) * t,
+        )
+    }
+
+    /// Moves along the path by `delta` seconds
+    pub fn advance(&mut self, delta: f32) {
+        if self.waypoints.len() < 2 || self.progress.finished {
+            return;
+        }
+        self.progress.elapsed += delta;
+
+        // a long frame may cover several legs, cap it in case every leg takes no time
+        for _ in 0..2 * self.waypoints.len() {
+            let leg_time =
+                self.leg().travel_time.max(0.0) + self.waypoints[self.progress.to].wait.max(0.0);
+            if self.progress.elapsed < leg_time {
+                return;
+            }
+            self.progress.elapsed -= leg_time;
+
+            let arrived = self.progress.to;
+            match self.next_stop(arrived, self.progress.reverse) {
+                Some((next, reverse)) => {
+                    self.progress.from = arrived;
+                    self.progress.to = next;
+                    self.progress.reverse = reverse;
+                }
+                None => {
+                    self.progress.from = arrived;
+                    self.progress.elapsed = 0.0;
+                    self.progress.finished = true;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Keeps a dynamic body from being carried by the kinematic platforms it rests on, leaving
+/// only friction to move it. On a platform, keeps it from carrying anything.
+#[derive(Component, Clone, Copy, Default, Reflect)]
+pub struct NoPlatformCarry;
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Sets each platform's velocities so that this physics step ends exactly on its path
+pub fn move_kinematic_platforms(
+    time: Res<Time<Physics>>,
+    mut platform_query: Query<(
+        &mut KinematicPlatform,
+        &Position,
+        &Rotation,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+    )>,
+) {
+    let delta_secs = time.delta_seconds();
+    if delta_secs <= 0.0 {
+        return;
+    }
+
+    for (mut platform, position, rotation, mut linear_velocity, mut angular_velocity) in
+        platform_query.iter_mut()
+    {
+        platform.advance(delta_secs);
+        let (target_translation, target_rotation) = platform.pose();
+
+        linear_velocity.0 = (target_translation - position.0) / delta_secs;
+        // shortest way round, the current rotation is wrapped to -PI..=PI
+        let mut turn = (target_rotation - rotation.as_radians()).rem_euclid(TAU);
+        if turn > PI {
+            turn -= TAU;
+        }
+        angular_velocity.0 = turn / delta_secs;
+    }
+}
+
+/// Velocity of `point` on a body moving with the given velocities
+fn point_velocity(
+    center: Vector,
+    linear_velocity: Vector,
+    angular_velocity: f32,
+    point: Vector,
+) -> Vector {
+    linear_velocity + angular_velocity * (point - center).perp()
+}
+
+/// What a rider was last given by the platform it rests on
+#[derive(Clone, Copy)]
+pub struct CarriedVelocity {
+    linear: Vector,
+    angular: f32,
+    /// physics steps since the rider last touched the platform
+    missed_steps: u32,
+}
+
+/// Makes each dynamic body resting on a platform follow changes in the platform's velocity at
+/// the contact point, and in its angular velocity. Only the change since the last step is added
+/// so the rider keeps its own motion relative to the platform, and whatever it carried when it
+/// leaves. A body stepping on is matched to the platform by friction as usual, adding the
+/// platform's whole velocity would stack it on top of what the body already carries.
+pub fn carry_platform_riders(
+    gravity: Res<Gravity>,
+    collisions: Res<Collisions>,
+    platform_query: Query<
+        (&Position, &Rotation, &LinearVelocity, &AngularVelocity),
+        (With<KinematicPlatform>, Without<NoPlatformCarry>),
+    >,
+    mut rider_query: Query<
+        (&RigidBody, &mut LinearVelocity, &mut AngularVelocity),
+        (Without<KinematicPlatform>, Without<NoPlatformCarry>),
+    >,
+    mut carried: Local<HashMap<Entity, CarriedVelocity>>,
+) {
+    let up = (-gravity.0).try_normalize().unwrap_or(Vector::Y);
+    let min_alignment = MAX_RIDE_ANGLE.to_radians().cos();
+
+    // what every rider gets this step, from the contacts of the previous one
+    let mut carry: HashMap<Entity, (Vector, f32)> = HashMap::new();
+    for contacts in collisions
+        .iter()
+        .filter(|contacts| contacts.during_current_frame)
+    {
+        for platform_is_first in [true, false] {
+            let (platform_entity, rider_entity) = if platform_is_first {
+                (contacts.entity1, contacts.entity2)
+            } else {
+                (contacts.entity2, contacts.entity1)
+            };
+            let Ok((position, rotation, linear_velocity, angular_velocity)) =
+                platform_query.get(platform_entity)
+            else {
+                continue;
+            };
+
+            // contact normals and points are in the platform's local space, the normal points
+            // from the platform towards the rider
+            let mut points = Vec::new();
+            for manifold in contacts.manifolds.iter() {
+                let normal = rotation.rotate(if platform_is_first {
+                    manifold.normal1
+                } else {
+                    manifold.normal2
+                });
+                let Some(normal) = normal.try_normalize() else {
+                    continue;
+                };
+                if normal.dot(up) < min_alignment {
+                    continue;
+                }
+                points.extend(manifold.contacts.iter().map(|contact| {
+                    position.0
+                        + rotation.rotate(if platform_is_first {
+                            contact.point1
+                        } else {
+                            contact.point2
+                        })
+                }));
+            }
+            if points.is_empty() {
+                continue;
+            }
+
+            let point = points.iter().sum::<Vector>() / points.len() as f32;
+            carry.insert(
+                rider_entity,
+                (
+                    point_velocity(position.0, linear_velocity.0, angular_velocity.0, point),
+                    angular_velocity.0,
+                ),
+            );
+        }
+    }
+
+    for (&rider_entity, &(linear, angular)) in carry.iter() {
+        let Ok((rigid_body, mut linear_velocity, mut angular_velocity)) =
+            rider_query.get_mut(rider_entity)
+        else {
+            continue;
+        };
+        if !rigid_body.is_dynamic() {
+            continue;
+        }
+        if let Some(previous) = carried.get(&rider_entity) {
+            linear_velocity.0 += linear - previous.linear;
+            angular_velocity.0 += angular - previous.angular;
+        }
+    }
+
+    for remembered in carried.values_mut() {
+        remembered.missed_steps += 1;
+    }
+    carried.retain(|_, remembered| remembered.missed_steps <= RIDER_GRACE_STEPS);
+    for (rider_entity, (linear, angular)) in carry {
+        carried.insert(
+            rider_entity,
+            CarriedVelocity {
+                linear,
+                angular,
+                missed_steps: 0,
+            },
+        );
+    }
+}
+
+pub fn draw_platform_paths(mut gizmos: Gizmos, platform_query: Query<&KinematicPlatform>) {
+    for platform in platform_query.iter() {
+        let points = platform
+            .waypoints()
+            .iter()
+            .map(|waypoint| waypoint.translation);
+        if platform.mode() == PathMode::Loop {
+            gizmos.linestrip_2d(points.clone().chain(points.clone().take(1)), PATH_COLOR);
+        } else {
+            gizmos.linestrip_2d(points.clone(), PATH_COLOR);
+        }
+        for point in points {
+            gizmos.circle_2d(point, STOP_RADIUS, PATH_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(mode: PathMode, travel_time: f32) -> KinematicPlatform {
+        KinematicPlatform::new(
+            mode,
+            Waypoint::new(Vec2::new(0.0, 0.0), travel_time),
+            [
+                Waypoint::new(Vec2::new(10.0, 0.0), travel_time),
+                Waypoint::new(Vec2::new(10.0, 10.0), travel_time),
+            ],
+        )
+    }
+
+    fn legs(platform: &KinematicPlatform) -> (usize, usize) {
+        (platform.progress().from, platform.progress().to)
+    }
+
+    #[test]
+    fn loop_wraps_to_the_first_waypoint() {
+        let mut platform = path(PathMode::Loop, 1.0);
+        assert_eq!(platform.next_stop(2, false), Some((0, false)));
+
+        platform.advance(1.0);
+        assert_eq!(legs(&platform), (1, 2));
+        platform.advance(2.0);
+        assert_eq!(legs(&platform), (0, 1));
+        assert!(!platform.progress().finished);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let mut platform = path(PathMode::PingPong, 1.0);
+        assert_eq!(platform.next_stop(0, false), Some((1, false)));
+        assert_eq!(platform.next_stop(2, false), Some((1, true)));
+        assert_eq!(platform.next_stop(1, true), Some((0, true)));
+        assert_eq!(platform.next_stop(0, true), Some((1, false)));
+
+        platform.advance(2.0);
+        assert_eq!(legs(&platform), (2, 1));
+        assert!(platform.progress().reverse);
+        platform.advance(2.0);
+        assert_eq!(legs(&platform), (0, 1));
+        assert!(!platform.progress().reverse);
+    }
+
+    #[test]
+    fn once_stops_at_the_last_waypoint() {
+        let mut platform = path(PathMode::Once, 1.0);
+        assert_eq!(platform.next_stop(2, false), None);
+
+        platform.advance(5.0);
+        assert!(platform.progress().finished);
+        assert_eq!(platform.pose(), (Vec2::new(10.0, 10.0), 0.0));
+        platform.advance(1.0);
+        assert_eq!(platform.pose(), (Vec2::new(10.0, 10.0), 0.0));
+    }
+
+    #[test]
+    fn pose_interpolates_along_the_leg() {
+        let mut platform = path(PathMode::Loop, 2.0);
+        platform.advance(1.0);
+        assert_eq!(platform.pose(), (Vec2::new(5.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn zero_travel_time_does_not_hang() {
+        for mode in [PathMode::Loop, PathMode::PingPong, PathMode::Once] {
+            let mut platform = path(mode, 0.0);
+            platform.advance(0.1);
+            let (translation, rotation) = platform.pose();
+            assert!(translation.is_finite() && rotation.is_finite());
+        }
+    }
+
+    #[test]
+    fn single_waypoint_stays_put() {
+        let mut platform =
+            KinematicPlatform::new(PathMode::Loop, Waypoint::new(Vec2::new(3.0, 4.0), 1.0), []);
+        platform.advance(10.0);
+        assert_eq!(platform.pose(), (Vec2::new(3.0, 4.0), 0.0));
+    }
+
+    #[test]
+    fn deserializing_checks_waypoints_and_progress() {
+        assert!(ron::from_str::<KinematicPlatform>("(waypoints: [])").is_err());
+        assert!(ron::from_str::<KinematicPlatform>(
+            "(waypoints: [(translation: (0.0, 0.0)), (translation: (1.0, 0.0))], \
+             progress: (from: 0, to: 5, reverse: false, elapsed: 0.0, finished: false))"
+        )
+        .is_err());
+
+        let mut platform = path(PathMode::PingPong, 1.0);
+        platform.advance(2.5);
+        let ron = ron::to_string(&platform).unwrap();
+        let loaded: KinematicPlatform = ron::from_str(&ron).unwrap();
+        assert_eq!(loaded.progress(), platform.progress());
+    }
+}
@@ -0,0 +1,229 @@
+use super::{
+    demo_state::{enter_demo_now, DemoGeneration, DemoRegistry, DemoScoped, DemoState},
+    snapshot::{spawn_entities, EntitySnapshot},
+};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    ecs::event::ManualEventReader,
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+
+use std::{error::Error, fmt, fs, path::Path};
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // assets ----------------------------------------------------------
+            .init_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            // resources -------------------------------------------------------
+            .init_resource::<DemoLevels>()
+            // Startup ---------------------------------------------------------
+            .add_systems(Startup, load_demo_levels)
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    queue_level_spawn.run_if(resource_changed::<DemoGeneration>),
+                    spawn_pending_level,
+                    reload_modified_levels,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// level format ----------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// A demo layout, stored as `*.level.ron`. Uses the snapshot entity format, so a saved
+/// snapshot's `entities` can be pasted into a level as-is.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Default)]
+pub struct Level {
+    pub entities: Vec<EntitySnapshot>,
+}
+
+// asset loader ----------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Default)]
+pub struct LevelLoader;
+
+#[derive(Debug)]
+pub enum LevelLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for LevelLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read level: {err}"),
+            Self::Ron(err) => write!(f, "could not parse level: {err}"),
+        }
+    }
+}
+
+impl Error for LevelLoaderError {}
+
+impl From<std::io::Error> for LevelLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Level, LevelLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn write_level(level: &Level, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let config =
+        ron::ser::PrettyConfig::default().extensions(ron::extensions::Extensions::IMPLICIT_SOME);
+    fs::write(path, ron::ser::to_string_pretty(level, config)?)?;
+    Ok(())
+}
+
+pub fn read_level(path: &Path) -> Result<Level, Box<dyn Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Handles to every registered demo's level, loaded up front so entering a demo is instant
+#[derive(Resource, Default)]
+pub struct DemoLevels(pub HashMap<DemoState, Handle<Level>>);
+
+impl DemoLevels {
+    /// Whether every level has either loaded or failed to
+    pub fn all_settled(&self, asset_server: &AssetServer) -> bool {
+        self.0.values().all(|handle| {
+            matches!(
+                asset_server.get_load_state(handle),
+                Some(LoadState::Loaded | LoadState::Failed)
+            )
+        })
+    }
+}
+
+/// The running demo's level, waiting for its asset to finish loading
+#[derive(Resource)]
+pub struct PendingLevel {
+    pub demo: DemoState,
+    pub handle: Handle<Level>,
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn load_demo_levels(
+    asset_server: Res<AssetServer>,
+    registry: Res<DemoRegistry>,
+    mut levels: ResMut<DemoLevels>,
+) {
+    for demo in registry.iter() {
+        if let Some(path) = demo.level {
+            levels.0.insert(demo.state, asset_server.load(path));
+        }
+    }
+}
+
+/// Runs whenever a demo is entered or restarted
+pub fn queue_level_spawn(
+    mut commands: Commands,
+    demo_state: Res<State<DemoState>>,
+    levels: Res<DemoLevels>,
+) {
+    let demo = *demo_state.get();
+    if let Some(handle) = levels.0.get(&demo) {
+        commands.insert_resource(PendingLevel {
+            demo,
+            handle: handle.clone(),
+        });
+    }
+}
+
+pub fn spawn_pending_level(world: &mut World) {
+    let Some(pending) = world.get_resource::<PendingLevel>() else {
+        return;
+    };
+    let demo = pending.demo;
+    let handle = pending.handle.clone();
+
+    // the demo changed before its level finished loading
+    if *world.resource::<State<DemoState>>().get() != demo {
+        world.remove_resource::<PendingLevel>();
+        return;
+    }
+
+    let Some(level) = world.resource::<Assets<Level>>().get(&handle) else {
+        if let Some(LoadState::Failed) = world.resource::<AssetServer>().get_load_state(&handle) {
+            error!("level for {demo:?} failed to load");
+            world.remove_resource::<PendingLevel>();
+        }
+        return;
+    };
+
+    let entities = level.entities.clone();
+    spawn_entities(world, &entities, DemoScoped(demo));
+    world.remove_resource::<PendingLevel>();
+}
+
+/// Restarts the running demo when its level file changes on disk
+pub fn reload_modified_levels(
+    world: &mut World,
+    mut reader: Local<ManualEventReader<AssetEvent<Level>>>,
+) {
+    let modified: Vec<AssetId<Level>> = reader
+        .read(world.resource::<Events<AssetEvent<Level>>>())
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if modified.is_empty() {
+        return;
+    }
+
+    let demo = *world.resource::<State<DemoState>>().get();
+    let Some(handle) = world.resource::<DemoLevels>().0.get(&demo) else {
+        return;
+    };
+    if modified.contains(&handle.id()) {
+        info!("level for {demo:?} changed, restarting the demo");
+        enter_demo_now(world, demo);
+    }
+}
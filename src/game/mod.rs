@@ -1,5 +1,19 @@
-mod camera;
+pub mod bindings;
+pub mod camera;
+pub mod demo_menu;
 pub mod demo_state;
+pub mod editor;
 pub mod game_plugin;
-mod physics_demos;
-mod player;
+pub mod headless;
+pub mod kinematic_platform;
+pub mod level;
+pub mod mouse_drag;
+pub mod one_way_platform;
+pub mod physics_demos;
+pub mod platformer;
+pub mod player;
+pub mod replay;
+pub mod rewind;
+pub mod snapshot;
+pub mod time_controls;
+pub mod tuning;
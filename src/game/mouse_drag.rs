@@ -0,0 +1,194 @@
+use super::{camera::WorldCursor, editor::editor_active, tuning::TuningAppExt};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Left-drag pulls a dynamic body towards the cursor on a spring,
+/// right-drag does the same and throws the body on release
+pub struct MouseDragPlugin;
+
+impl Plugin for MouseDragPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // tuning ----------------------------------------------------------
+            .add_tuning::<MouseDragSettings>(None)
+            // resources -------------------------------------------------------
+            .init_resource::<MouseDrag>()
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    begin_mouse_drag.run_if(not(editor_active)),
+                    move_mouse_anchor,
+                    end_mouse_drag,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct MouseDragSettings {
+    /// spring stiffness, the joint's compliance is its inverse
+    #[inspector(min = 0.001)]
+    pub stiffness: f32,
+    #[inspector(min = 0.0)]
+    pub damping: f32,
+    /// multiplies the cursor velocity given to a body thrown with the right button
+    #[inspector(min = 0.0)]
+    pub flick_scale: f32,
+}
+
+impl Default for MouseDragSettings {
+    fn default() -> Self {
+        Self {
+            stiffness: 50.0,
+            damping: 1.0,
+            flick_scale: 1.0,
+        }
+    }
+}
+
+pub struct ActiveDrag {
+    pub body: Entity,
+    /// kinematic body following the cursor, the spring's other end
+    pub anchor: Entity,
+    pub joint: Entity,
+    pub button: MouseButton,
+}
+
+#[derive(Resource, Default)]
+pub struct MouseDrag(pub Option<ActiveDrag>);
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component)]
+pub struct MouseAnchor;
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn begin_mouse_drag(
+    mut commands: Commands,
+    mut drag: ResMut<MouseDrag>,
+    cursor: Res<WorldCursor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    spatial_query: SpatialQuery,
+    collider_query: Query<&ColliderParent>,
+    body_query: Query<(&RigidBody, &Position, &Rotation)>,
+) {
+    if drag.0.is_some() || cursor.over_ui {
+        return;
+    }
+    let Some(button) = [MouseButton::Left, MouseButton::Right]
+        .into_iter()
+        .find(|button| mouse.just_pressed(*button))
+    else {
+        return;
+    };
+    let Some(cursor_position) = cursor.position else {
+        return;
+    };
+
+    // the first dynamic body with a collider under the cursor
+    let Some((body, position, rotation)) = spatial_query
+        .point_intersections(cursor_position, SpatialQueryFilter::default())
+        .into_iter()
+        .filter_map(|collider| collider_query.get(collider).ok())
+        .filter_map(|parent| {
+            let (rigid_body, position, rotation) = body_query.get(parent.get()).ok()?;
+            rigid_body
+                .is_dynamic()
+                .then_some((parent.get(), position, rotation))
+        })
+        .next()
+    else {
+        return;
+    };
+
+    // hold the body where it was clicked rather than by its center
+    let grab_point = (Quat::from_rotation_z(-rotation.as_radians())
+        * (cursor_position - position.0).extend(0.0))
+    .xy();
+
+    let anchor = commands
+        .spawn((
+            Name::new("MouseAnchor"),
+            MouseAnchor,
+            RigidBody::Kinematic,
+            TransformBundle::from_transform(Transform::from_translation(
+                cursor_position.extend(0.0),
+            )),
+        ))
+        .id();
+    let joint = commands
+        .spawn((
+            Name::new("MouseDragJoint"),
+            DistanceJoint::new(anchor, body)
+                .with_local_anchor_2(grab_point)
+                .with_rest_length(0.0),
+        ))
+        .id();
+
+    drag.0 = Some(ActiveDrag {
+        body,
+        anchor,
+        joint,
+        button,
+    });
+}
+
+pub fn move_mouse_anchor(
+    drag: Res<MouseDrag>,
+    settings: Res<MouseDragSettings>,
+    cursor: Res<WorldCursor>,
+    mut anchor_query: Query<&mut Position, With<MouseAnchor>>,
+    mut joint_query: Query<&mut DistanceJoint>,
+) {
+    let Some(active) = &drag.0 else {
+        return;
+    };
+
+    if let (Some(cursor_position), Ok(mut anchor_position)) =
+        (cursor.position, anchor_query.get_mut(active.anchor))
+    {
+        anchor_position.0 = cursor_position;
+    }
+    // follow the tuning panel while dragging
+    if let Ok(mut joint) = joint_query.get_mut(active.joint) {
+        joint.compliance = 1.0 / settings.stiffness;
+        joint.damping_linear = settings.damping;
+    }
+}
+
+pub fn end_mouse_drag(
+    mut commands: Commands,
+    mut drag: ResMut<MouseDrag>,
+    settings: Res<MouseDragSettings>,
+    cursor: Res<WorldCursor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut body_query: Query<&mut LinearVelocity>,
+) {
+    let Some(active) = &drag.0 else {
+        return;
+    };
+    // the body may have been despawned, e.g. by switching demos
+    let body_exists = body_query.contains(active.body);
+    if body_exists && mouse.pressed(active.button) {
+        return;
+    }
+
+    if active.button == MouseButton::Right {
+        if let Ok(mut linear_velocity) = body_query.get_mut(active.body) {
+            linear_velocity.0 = cursor.velocity * settings.flick_scale;
+        }
+    }
+    commands.entity(active.joint).despawn_recursive();
+    commands.entity(active.anchor).despawn_recursive();
+    drag.0 = None;
+}
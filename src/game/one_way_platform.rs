@@ -0,0 +1,158 @@
+use super::tuning::TuningAppExt;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_inspector_egui::prelude::*;
+use bevy_xpbd_2d::{
+    math::{Scalar, Vector},
+    prelude::*,
+    PostProcessCollisions,
+};
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Lets bodies pass through `OneWayPlatform`s from below, in every demo
+pub struct OneWayPlatformPlugin;
+
+impl Plugin for OneWayPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // tuning ----------------------------------------------------------
+            .add_tuning::<OneWayPlatformSettings>(None)
+            // PostProcessCollisions -------------------------------------------
+            .add_systems(PostProcessCollisions, one_way_platform);
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct OneWayPlatformSettings {
+    /// degrees a contact normal may lean away from the platform's up and still count as
+    /// landing on top of it, applied live
+    #[inspector(min = 0.0, max = 90.0)]
+    pub max_angle: f32,
+}
+
+impl Default for OneWayPlatformSettings {
+    fn default() -> Self {
+        Self { max_angle: 60.0 }
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Solid from the side its local +Y points to, passable from every other side.
+/// Holds the entities currently passing through it.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct OneWayPlatform(HashSet<Entity>);
+
+impl OneWayPlatform {
+    /// Whether the collision with `other` should happen. `from_above` is whether every contact
+    /// normal is in line with the platform's up vector.
+    fn allows(
+        &mut self,
+        other: Entity,
+        pass_through: PassThroughOneWayPlatform,
+        from_above: bool,
+        penetrating: bool,
+    ) -> bool {
+        if self.0.contains(&other) {
+            // If we were already allowing a collision for a particular entity,
+            // and if it is penetrating us still, continue to allow it to do so.
+            if penetrating {
+                return false;
+            }
+            // If it's no longer penetrating us, forget it.
+            self.0.remove(&other);
+        }
+
+        match pass_through {
+            // Pass-through is set to never, so accept the collision.
+            PassThroughOneWayPlatform::Never => true,
+            // Pass-through is set to always, so always ignore this collision
+            // and register it as an entity that's currently penetrating.
+            PassThroughOneWayPlatform::Always => {
+                self.0.insert(other);
+                false
+            }
+            // The entity is on top of the platform.
+            PassThroughOneWayPlatform::ByNormal if from_above => true,
+            // If it's already penetrating, ignore the collision and register
+            // the other entity as one that's currently penetrating.
+            PassThroughOneWayPlatform::ByNormal if penetrating => {
+                self.0.insert(other);
+                false
+            }
+            // In all other cases, allow this collision.
+            PassThroughOneWayPlatform::ByNormal => true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component, Reflect)]
+pub enum PassThroughOneWayPlatform {
+    #[default]
+    /// Passes through a `OneWayPlatform` if the contact normal is in line with the platform's local-space up vector
+    ByNormal,
+    /// Always passes through a `OneWayPlatform`, temporarily set this to allow an actor to jump down through a platform
+    Always,
+    /// Never passes through a `OneWayPlatform`
+    Never,
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Filters collisions with one-way platforms. A collision between two platforms has to be
+/// allowed by both of them.
+pub fn one_way_platform(
+    settings: Res<OneWayPlatformSettings>,
+    mut platform_query: Query<&mut OneWayPlatform>,
+    pass_through_query: Query<&PassThroughOneWayPlatform>,
+    mut collisions: ResMut<Collisions>,
+) {
+    let min_alignment = settings.max_angle.to_radians().cos();
+
+    // This assumes that Collisions contains empty entries for entities
+    // that were once colliding but no longer are.
+    collisions.retain(|contacts| {
+        let penetrating = contacts.manifolds.iter().any(|manifold| {
+            manifold
+                .contacts
+                .iter()
+                .any(|contact| contact.penetration > 0.0)
+        });
+
+        let mut keep = true;
+        for platform_is_first in [true, false] {
+            let (platform_entity, other_entity) = if platform_is_first {
+                (contacts.entity1, contacts.entity2)
+            } else {
+                (contacts.entity2, contacts.entity1)
+            };
+            let Ok(mut platform) = platform_query.get_mut(platform_entity) else {
+                continue;
+            };
+
+            // manifold normals are in the local space of their entity, so the platform's own
+            // normal can be compared with its local up as is
+            let from_above = contacts.manifolds.iter().all(|manifold| {
+                let normal = if platform_is_first {
+                    manifold.normal1
+                } else {
+                    manifold.normal2
+                };
+                normal.length() > Scalar::EPSILON
+                    && normal.normalize().dot(Vector::Y) >= min_alignment
+            });
+
+            let pass_through = pass_through_query
+                .get(other_entity)
+                .copied()
+                .unwrap_or_default();
+            // evaluate both platforms so each keeps track of what passes through it
+            keep &= platform.allows(other_entity, pass_through, from_above, penetrating);
+        }
+        keep
+    });
+}
@@ -1,122 +1,88 @@
-use crate::game::game_plugin::GravityFactor;
+use crate::game::{
+    demo_state::{Demo, DemoGeneration, DemoScoped, DemoState},
+    game_plugin::GravityFactor,
+    level::spawn_pending_level,
+    physics_demos::{grow_static_bodies, scaled_count},
+    player::Player,
+    snapshot::{capture_entities, spawn_entities},
+    tuning::TuningAppExt,
+};
 
-use bevy::{prelude::*, utils::Duration};
+use bevy::{ecs::schedule::SystemConfigs, prelude::*, utils::Duration};
+use bevy_inspector_egui::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 
-// components ------------------------------------------------------------------
+// constants -------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Component)]
-pub struct CollidersDemo;
+/// vertical distance between copies of the level's shapes, the tallest is 14 high
+const COPY_SPACING: f32 = 16.0;
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct CollidersParams {
+    /// copies of every shape in the level, stacked upwards, applied on reset. The walls grow
+    /// to fit them
+    #[inspector(min = 1.0, max = 100.0)]
+    pub scale: f32,
+}
 
+impl Default for CollidersParams {
+    fn default() -> Self {
+        Self { scale: 1.0 }
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
 #[derive(Component)]
 pub struct CollidersTimer {
     pub timer: Timer,
 }
 
-// systems ---------------------------------------------------------------------
+// demo ------------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-pub fn spawn_colliders_demo(mut commands: Commands) {
-    commands
-        .spawn((
-            Name::new("CollidersBottomWall"),
-            CollidersDemo,
-            RigidBody::Static,
-            Collider::rectangle(180.0, 10.0),
-            TransformBundle::from_transform(Transform::from_xyz(0.0, -50.0, 0.0)),
-            DebugRender {
-                axis_lengths: None,
-                collider_color: Some(Color::WHITE),
-                ..default()
-            },
-        ))
-        .with_children(|children| {
-            children.spawn((
-                Name::new("CollidersLeftWall"),
-                Collider::rectangle(10.0, 110.0),
-                TransformBundle::from_transform(Transform::from_xyz(-95.0, 50.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-            children.spawn((
-                Name::new("CollidersRightWall"),
-                Collider::rectangle(10.0, 110.0),
-                TransformBundle::from_transform(Transform::from_xyz(95.0, 50.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-            children.spawn((
-                Name::new("CollidersTopWall"),
-                Collider::rectangle(180.0, 10.0),
-                TransformBundle::from_transform(Transform::from_xyz(0.0, 100.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-        });
+pub struct CollidersDemo;
 
-    commands.spawn((
-        Name::new("CollidersCircle"),
-        CollidersDemo,
-        RigidBody::Dynamic,
-        Collider::circle(5.0),
-        Restitution::new(0.9).with_combine_rule(CoefficientCombine::Max),
-        TransformBundle::from_transform(Transform::from_xyz(-80.0, 0.0, 0.0)),
-    ));
+impl Demo for CollidersDemo {
+    const STATE: DemoState = DemoState::Colliders;
+    const NAME: &'static str = "Colliders";
+    const DESCRIPTION: &'static str = "Every collider shape, dropped in a pile";
+    const LEVEL: Option<&'static str> = Some("levels/colliders.level.ron");
 
-    commands.spawn((
-        Name::new("CollidersEllipse"),
-        CollidersDemo,
-        RigidBody::Dynamic,
-        Collider::ellipse(5.0, 7.0),
-        TransformBundle::from_transform(Transform::from_xyz(-60.0, 0.0, 0.0)),
-    ));
+    fn spawn_systems() -> SystemConfigs {
+        spawn_colliders_demo.into_configs()
+    }
 
-    commands.spawn((
-        Name::new("CollidersRoundRectangle"),
-        CollidersDemo,
-        RigidBody::Dynamic,
-        Collider::round_rectangle(9.0, 5.0, 1.5),
-        GravityScale(-1.0),
-        TransformBundle::from_transform(Transform::from_xyz(-40.0, 0.0, 0.0)),
-    ));
+    fn update_systems() -> Option<SystemConfigs> {
+        Some(copy_collider_shapes.after(spawn_pending_level))
+    }
 
-    commands.spawn((
-        Name::new("CollidersTriangle"),
-        CollidersDemo,
-        RigidBody::Dynamic,
-        Collider::triangle(
-            Vec2::new(-5.0, -5.0),
-            Vec2::new(5.0, -5.0),
-            Vec2::new(0.0, 5.0),
-        ),
-        TransformBundle::from_transform(Transform::from_xyz(40.0, 0.0, 0.0)),
-    ));
+    fn physics_systems() -> Option<SystemConfigs> {
+        Some(rotate_gravity.into_configs())
+    }
 
-    commands.spawn((
-        Name::new("CollidersRegPolygon"),
-        CollidersDemo,
-        RigidBody::Dynamic,
-        Collider::regular_polygon(6.0, 9),
-        LockedAxes::ROTATION_LOCKED,
-        TransformBundle::from_transform(Transform::from_xyz(60.0, 0.0, 0.0)),
-    ));
+    fn build(app: &mut App) {
+        app.add_tuning::<CollidersParams>(Some(Self::STATE));
+    }
+}
 
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn spawn_colliders_demo(mut commands: Commands) {
+    // walls and shapes come from the level file
     commands.spawn((
         Name::new("CollidersTimer"),
-        CollidersDemo,
+        DemoScoped(DemoState::Colliders),
         CollidersTimer {
             timer: Timer::new(Duration::from_secs(5), TimerMode::Repeating),
         },
     ));
 }
 
-pub fn despawn_colliders_demo(
-    mut commands: Commands,
-    demo_query: Query<Entity, With<CollidersDemo>>,
-) {
-    for demo_entity in demo_query.iter() {
-        commands.entity(demo_entity).despawn_recursive();
-    }
-}
-
 pub fn rotate_gravity(
-    time: Res<Time>,
+    time: Res<Time<Physics>>,
     mut timer_query: Query<&mut CollidersTimer>,
     mut gravity: ResMut<Gravity>,
     g_factor: Res<GravityFactor>,
@@ -133,3 +99,48 @@ pub fn rotate_gravity(
         }
     }
 }
+
+/// Copies the level's shapes once they have spawned, once per demo entry, growing the walls
+/// around them
+pub fn copy_collider_shapes(world: &mut World, mut copied: Local<Option<DemoGeneration>>) {
+    let generation = *world.resource::<DemoGeneration>();
+    if *copied == Some(generation) {
+        return;
+    }
+
+    let shapes: Vec<Entity> = world
+        .query_filtered::<(Entity, &RigidBody), (With<DemoScoped>, Without<Player>, Without<Parent>)>()
+        .iter(world)
+        .filter(|(_, rigid_body)| rigid_body.is_dynamic())
+        .map(|(entity, _)| entity)
+        .collect();
+    // the level hasn't spawned yet
+    if shapes.is_empty() {
+        return;
+    }
+    *copied = Some(generation);
+
+    let copies = scaled_count(1, world.resource::<CollidersParams>().scale) - 1;
+    if copies == 0 {
+        return;
+    }
+    // the stack starts at the origin, halfway up the box's 90 high inside
+    let factor = ((copies as f32 * COPY_SPACING + 10.0) / 45.0).max(1.0);
+    grow_static_bodies(world, factor);
+
+    let snapshots = capture_entities(world, &shapes);
+    for copy in 1..=copies {
+        let shifted: Vec<_> = snapshots
+            .iter()
+            .cloned()
+            .map(|mut snapshot| {
+                // children stay where they are relative to their parent
+                if snapshot.parent.is_none() {
+                    snapshot.translation.y += copy as f32 * COPY_SPACING;
+                }
+                snapshot
+            })
+            .collect();
+        spawn_entities(world, &shifted, DemoScoped(DemoState::Colliders));
+    }
+}
@@ -1,12 +1,46 @@
-use bevy::{prelude::*, utils::Duration};
+use crate::game::{
+    demo_state::{Demo, DemoScoped, DemoState},
+    physics_demos::scaled_count,
+    tuning::TuningAppExt,
+};
+
+use bevy::{ecs::schedule::SystemConfigs, prelude::*, utils::Duration};
+use bevy_inspector_egui::prelude::*;
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-// components ------------------------------------------------------------------
+// resources -------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Component)]
-pub struct ConveyorBeltDemo;
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct ConveyorBeltParams {
+    /// impulse applied by every belt to bodies touching it, applied live
+    pub belt_speed: f32,
+    /// seconds between new blocks, applied on reset
+    #[inspector(min = 0.1)]
+    pub block_interval: f32,
+    /// seconds before a block despawns, applied to new blocks
+    #[inspector(min = 0.1)]
+    pub block_lifetime: f32,
+    /// multiplies the blocks dropped each interval, stacked above the drop point, applied live
+    #[inspector(min = 0.1, max = 100.0)]
+    pub scale: f32,
+}
 
-#[derive(Component)]
+impl Default for ConveyorBeltParams {
+    fn default() -> Self {
+        Self {
+            belt_speed: 20.0,
+            block_interval: 3.0,
+            block_lifetime: 20.0,
+            scale: 1.0,
+        }
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct ConveyorBelt {
     pub belt_vector: Vec2,
 }
@@ -16,114 +50,82 @@ pub struct ConveyorBeltDemoTimer {
     pub timer: Timer,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct ConveyorBeltBlockTimer {
     pub timer: Timer,
 }
 
-// systems ---------------------------------------------------------------------
+// demo ------------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-pub fn spawn_conveyor_belt_demo(mut commands: Commands) {
-    commands.spawn((
-        Name::new("ConveyorBottomWall"),
-        ConveyorBeltDemo,
-        RigidBody::Static,
-        Collider::rectangle(180.0, 10.0),
-        TransformBundle::from_transform(Transform::from_xyz(0.0, -50.0, 0.0)),
-        DebugRender {
-            axis_lengths: None,
-            collider_color: Some(Color::WHITE),
-            ..default()
-        },
-    ));
+pub struct ConveyorBeltDemo;
 
-    commands
-        .spawn((
-            Name::new("ConveyorBeltBottom"),
-            ConveyorBeltDemo,
-            ConveyorBelt {
-                belt_vector: Vec2::new(20.0, 0.0),
-            },
-            RigidBody::Static,
-            Collider::round_rectangle(100.0, 2.0, 1.0),
-            TransformBundle::from_transform(Transform::from_xyz(-50.0, -20.0, 0.0)),
-        ))
-        .with_children(|children| {
-            children.spawn((
-                Name::new("ConveyorBeltCasingBottom"),
-                RigidBody::Static,
-                Collider::rectangle(105.0, 5.0),
-                TransformBundle::from_transform(Transform::from_xyz(0.0, -1.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-        });
-
-    commands
-        .spawn((
-            Name::new("ConveyorBeltMiddle"),
-            ConveyorBeltDemo,
-            ConveyorBelt {
-                belt_vector: Vec2::new(-20.0, 0.0),
-            },
-            RigidBody::Static,
-            Collider::round_rectangle(100.0, 2.0, 1.0),
-            TransformBundle::from_transform(Transform::from_xyz(40.0, 15.0, 0.0)),
-        ))
-        .with_children(|children| {
-            children.spawn((
-                Name::new("ConveyorBeltCasingMiddle"),
-                RigidBody::Static,
-                Collider::rectangle(105.0, 5.0),
-                TransformBundle::from_transform(Transform::from_xyz(0.0, -1.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-        });
-
-    commands
-        .spawn((
-            Name::new("ConveyorBeltTop"),
-            ConveyorBeltDemo,
-            ConveyorBelt {
-                belt_vector: Vec2::new(20.0, 0.0),
+impl Demo for ConveyorBeltDemo {
+    const STATE: DemoState = DemoState::ConveyorBelt;
+    const NAME: &'static str = "Conveyor Belt";
+    const DESCRIPTION: &'static str = "Belts carrying a stream of blocks";
+    const LEVEL: Option<&'static str> = Some("levels/conveyor_belt.level.ron");
+
+    fn spawn_systems() -> SystemConfigs {
+        spawn_conveyor_belt_demo.into_configs()
+    }
+
+    fn update_systems() -> Option<SystemConfigs> {
+        Some((handle_belt_collisions, sync_belt_speed).into_configs())
+    }
+
+    fn physics_systems() -> Option<SystemConfigs> {
+        Some(spawn_and_despawn_blocks.into_configs())
+    }
+
+    fn build(app: &mut App) {
+        app.add_tuning::<ConveyorBeltParams>(Some(Self::STATE));
+    }
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// One block at the top left drop point, or a stack of them when scaled up
+fn spawn_blocks(commands: &mut Commands, params: &ConveyorBeltParams) {
+    for n in 0..scaled_count(1, params.scale) {
+        commands.spawn((
+            Name::new("ConveyorRoundRectangle"),
+            DemoScoped(DemoState::ConveyorBelt),
+            ConveyorBeltBlockTimer {
+                timer: Timer::new(
+                    Duration::from_secs_f32(params.block_lifetime),
+                    TimerMode::Repeating,
+                ),
             },
-            RigidBody::Static,
-            Collider::round_rectangle(100.0, 2.0, 1.0),
-            TransformBundle::from_transform(Transform::from_xyz(-40.0, 50.0, 0.0)),
-        ))
-        .with_children(|children| {
-            children.spawn((
-                Name::new("ConveyorBeltCasingTop"),
-                RigidBody::Static,
-                Collider::rectangle(105.0, 5.0),
-                TransformBundle::from_transform(Transform::from_xyz(0.0, -1.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-        });
+            RigidBody::Dynamic,
+            Restitution::new(0.1),
+            Collider::round_rectangle(8.0, 4.0, 1.5),
+            TransformBundle::from_transform(Transform::from_xyz(-80.0, 70.0 + n as f32 * 6.0, 0.0)),
+        ));
+    }
+}
 
-    commands.spawn((
-        Name::new("ConveyorRoundRectangle"),
-        ConveyorBeltDemo,
-        ConveyorBeltBlockTimer {
-            timer: Timer::new(Duration::from_secs(20), TimerMode::Repeating),
-        },
-        RigidBody::Dynamic,
-        Restitution::new(0.1),
-        Collider::round_rectangle(8.0, 4.0, 1.5),
-        TransformBundle::from_transform(Transform::from_xyz(-80.0, 70.0, 0.0)),
-    ));
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn spawn_conveyor_belt_demo(mut commands: Commands, params: Res<ConveyorBeltParams>) {
+    // walls and belts come from the level file
+    spawn_blocks(&mut commands, &params);
 
     commands.spawn((
         Name::new("ConveyorBeltDemoTimer"),
-        ConveyorBeltDemo,
+        DemoScoped(DemoState::ConveyorBelt),
         ConveyorBeltDemoTimer {
-            timer: Timer::new(Duration::from_secs(3), TimerMode::Repeating),
+            timer: Timer::new(
+                Duration::from_secs_f32(params.block_interval),
+                TimerMode::Repeating,
+            ),
         },
     ));
 }
 
 pub fn spawn_and_despawn_blocks(
     mut commands: Commands,
-    time: Res<Time>,
+    params: Res<ConveyorBeltParams>,
+    time: Res<Time<Physics>>,
     mut demo_timer_query: Query<&mut ConveyorBeltDemoTimer>,
     mut block_query: Query<(Entity, &mut ConveyorBeltBlockTimer)>,
 ) {
@@ -131,17 +133,7 @@ pub fn spawn_and_despawn_blocks(
     demo_timer.timer.tick(time.delta());
 
     if demo_timer.timer.just_finished() {
-        commands.spawn((
-            Name::new("ConveyorRoundRectangle"),
-            ConveyorBeltDemo,
-            ConveyorBeltBlockTimer {
-                timer: Timer::new(Duration::from_secs(20), TimerMode::Repeating),
-            },
-            RigidBody::Dynamic,
-            Restitution::new(0.1),
-            Collider::round_rectangle(8.0, 4.0, 1.5),
-            TransformBundle::from_transform(Transform::from_xyz(-80.0, 70.0, 0.0)),
-        ));
+        spawn_blocks(&mut commands, &params);
     }
 
     for (block_entity, mut block_timer) in block_query.iter_mut() {
@@ -153,15 +145,6 @@ pub fn spawn_and_despawn_blocks(
     }
 }
 
-pub fn despawn_conveyor_belt_demo(
-    mut commands: Commands,
-    demo_query: Query<Entity, With<ConveyorBeltDemo>>,
-) {
-    for demo_entity in demo_query.iter() {
-        commands.entity(demo_entity).despawn_recursive();
-    }
-}
-
 pub fn handle_belt_collisions(
     belt_query: Query<(&ConveyorBelt, &CollidingEntities)>,
     mut impulse_query: Query<&mut ExternalImpulse>,
@@ -174,3 +157,18 @@ pub fn handle_belt_collisions(
         }
     }
 }
+
+/// Keeps each belt's direction and rescales it to the tuned speed, including belts
+/// spawned from the level after the speed was last changed
+pub fn sync_belt_speed(params: Res<ConveyorBeltParams>, mut belt_query: Query<&mut ConveyorBelt>) {
+    for mut belt in belt_query.iter_mut() {
+        if !params.is_changed() && !belt.is_added() {
+            continue;
+        }
+        // reading doesn't mark the belt changed, only writing a new speed does
+        let speed = belt.belt_vector.x.signum() * params.belt_speed;
+        if belt.belt_vector.x != speed {
+            belt.belt_vector.x = speed;
+        }
+    }
+}
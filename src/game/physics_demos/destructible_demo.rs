@@ -1,8 +1,16 @@
 // code inspired by bevy_rapier_2d's "joints2.rs" example
 // https://github.com/dimforge/bevy_rapier/blob/master/bevy_rapier2d/examples/joints2.rs
 // -----------------------------------------------------------------------------
-use bevy::prelude::*;
+use crate::game::{
+    demo_state::{Demo, DemoScoped, DemoState},
+    physics_demos::scaled_count,
+    tuning::TuningAppExt,
+};
+
+use bevy::{ecs::schedule::SystemConfigs, prelude::*};
+use bevy_inspector_egui::prelude::*;
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 // hexagon tesselation  --------------------------------------------------------
 // -----------------------------------------------------------------------------
@@ -14,26 +22,111 @@ pub const HEX_X_SHIFT: f32 = 0.5 * HEX_WIDTH;
 pub const HEX_HEIGHT: f32 = 2.0 * HEX_RADIUS;
 pub const HEX_Y_SHIFT: f32 = 0.75 * HEX_HEIGHT;
 
-// components ------------------------------------------------------------------
+// resources -------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Component)]
-pub struct DestructibleDemo;
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct DestructibleParams {
+    /// impulse a joint can carry in one step before breaking, applied live
+    #[inspector(min = 0.0)]
+    pub impulse_threshold: f32,
+    /// compliance of the joints holding the grids together, applied on reset
+    #[inspector(min = 0.0)]
+    pub joint_compliance: f32,
+    /// multiplies the rows and columns of every grid (10 gives 100x100 grids) and stretches the
+    /// platform to fit, applied on reset
+    #[inspector(min = 0.1, max = 20.0)]
+    pub scale: f32,
+}
 
-#[derive(Component)]
+impl Default for DestructibleParams {
+    fn default() -> Self {
+        Self {
+            impulse_threshold: 10.0,
+            joint_compliance: 0.0000001,
+            scale: 1.0,
+        }
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Destructible {
     pub impulse_threshold: f32,
 }
 
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum DestructibleJointKind {
+    Prismatic,
+    Revolute,
+    Distance,
+}
+
+// events ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Event, Debug)]
+pub struct DestructibleJointBroken {
+    pub joint: Entity,
+    pub entity1: Entity,
+    pub entity2: Entity,
+    pub kind: DestructibleJointKind,
+    pub impulse: f32,
+}
+
+// demo ------------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct DestructibleDemo;
+
+impl Demo for DestructibleDemo {
+    const STATE: DemoState = DemoState::Destructible;
+    const NAME: &'static str = "Destructible";
+    const DESCRIPTION: &'static str = "Jointed grids that break apart under impulses";
+    // the platform spans x -80..520, leave room for the grids falling off either end
+    const CAMERA_BOUNDS: Option<Rect> = Some(Rect {
+        min: Vec2::new(-250.0, -200.0),
+        max: Vec2::new(600.0, 250.0),
+    });
+
+    fn spawn_systems() -> SystemConfigs {
+        (
+            spawn_destructible_platform,
+            spawn_destructible_prismatic_grid,
+            spawn_destructible_revolute_grid,
+            spawn_destructible_distance_grid,
+            spawn_destructible_static_grid,
+        )
+            .into_configs()
+    }
+
+    fn update_systems() -> Option<SystemConfigs> {
+        Some(
+            (
+                break_destructible_joints,
+                sync_impulse_thresholds.run_if(resource_changed::<DestructibleParams>),
+            )
+                .into_configs(),
+        )
+    }
+
+    fn build(app: &mut App) {
+        app.add_event::<DestructibleJointBroken>()
+            .add_tuning::<DestructibleParams>(Some(Self::STATE));
+    }
+}
+
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-pub fn spawn_destructible_platform(mut commands: Commands) {
+pub fn spawn_destructible_platform(mut commands: Commands, params: Res<DestructibleParams>) {
+    // grids are spread out along x with the scale, and grow upwards
+    let scale = params.scale;
     commands
         .spawn((
             Name::new("DestructiblePlatform"),
-            DestructibleDemo,
+            DemoScoped(DemoState::Destructible),
             RigidBody::Static,
-            Collider::rectangle(600.0, 10.0),
-            TransformBundle::from_transform(Transform::from_xyz(220.0, -50.0, 0.0)),
+            Collider::rectangle(600.0 * scale, 10.0),
+            TransformBundle::from_transform(Transform::from_xyz(220.0 * scale, -50.0, 0.0)),
             DebugRender {
                 axis_lengths: None,
                 collider_color: Some(Color::WHITE),
@@ -43,17 +136,21 @@ pub fn spawn_destructible_platform(mut commands: Commands) {
         .with_children(|children| {
             children.spawn((
                 Name::new("DestuctibleRightWall"),
-                Collider::rectangle(10.0, 110.0),
-                TransformBundle::from_transform(Transform::from_xyz(305.0, 50.0, 0.0)),
+                Collider::rectangle(10.0, 110.0 * scale),
+                TransformBundle::from_transform(Transform::from_xyz(
+                    300.0 * scale + 5.0,
+                    55.0 * scale - 5.0,
+                    0.0,
+                )),
                 DebugRender::default().with_collider_color(Color::WHITE),
             ));
         });
 }
 
-pub fn spawn_destructible_prismatic_grid(mut commands: Commands) {
-    let numi = 10; // number of VERTICAL hexagons (num rows)
-    let numk = 10; // number of HORIZONTAL hexagons (num columns)
-    let start_pos = Vec2::new(20.0, -35.0); // position of bottom left hexagon
+pub fn spawn_destructible_prismatic_grid(mut commands: Commands, params: Res<DestructibleParams>) {
+    let numi = scaled_count(10, params.scale); // number of VERTICAL hexagons (num rows)
+    let numk = scaled_count(10, params.scale); // number of HORIZONTAL hexagons (num columns)
+    let start_pos = Vec2::new(20.0 * params.scale, -35.0); // position of bottom left hexagon
 
     let mut body_entities = Vec::new();
 
@@ -80,9 +177,9 @@ pub fn spawn_destructible_prismatic_grid(mut commands: Commands) {
             // hexagon
             let child_entity = commands
                 .spawn((
-                    DestructibleDemo,
+                    DemoScoped(DemoState::Destructible),
                     Destructible {
-                        impulse_threshold: 10.0,
+                        impulse_threshold: params.impulse_threshold,
                     },
                     RigidBody::Dynamic,
                     GravityScale(0.01),
@@ -117,7 +214,7 @@ pub fn spawn_destructible_prismatic_grid(mut commands: Commands) {
                             .with_limits(-1.5, 1.5)
                             .with_linear_velocity_damping(0.5)
                             .with_angular_velocity_damping(0.5)
-                            .with_compliance(0.0000001),
+                            .with_compliance(params.joint_compliance),
                     );
                 });
             }
@@ -136,7 +233,7 @@ pub fn spawn_destructible_prismatic_grid(mut commands: Commands) {
                                 .with_limits(-1.5, 1.5)
                                 .with_linear_velocity_damping(0.5)
                                 .with_angular_velocity_damping(0.5)
-                                .with_compliance(0.0000001),
+                                .with_compliance(params.joint_compliance),
                         );
                     });
                 }
@@ -152,7 +249,7 @@ pub fn spawn_destructible_prismatic_grid(mut commands: Commands) {
                                 .with_limits(-1.5, 1.5)
                                 .with_linear_velocity_damping(0.5)
                                 .with_angular_velocity_damping(0.5)
-                                .with_compliance(0.0000001),
+                                .with_compliance(params.joint_compliance),
                         );
                     });
                 }
@@ -163,10 +260,10 @@ pub fn spawn_destructible_prismatic_grid(mut commands: Commands) {
     }
 }
 
-pub fn spawn_destructible_revolute_grid(mut commands: Commands) {
-    let numi = 10; // number of VERTICAL hexagons (num rows)
-    let numk = 10; // number of HORIZONTAL hexagons (num columns)
-    let start_pos = Vec2::new(170.0, -35.0); // position of bottom left hexagon
+pub fn spawn_destructible_revolute_grid(mut commands: Commands, params: Res<DestructibleParams>) {
+    let numi = scaled_count(10, params.scale); // number of VERTICAL hexagons (num rows)
+    let numk = scaled_count(10, params.scale); // number of HORIZONTAL hexagons (num columns)
+    let start_pos = Vec2::new(170.0 * params.scale, -35.0); // position of bottom left hexagon
 
     let mut body_entities = Vec::new();
 
@@ -186,9 +283,9 @@ pub fn spawn_destructible_revolute_grid(mut commands: Commands) {
             // hexagon
             let child_entity = commands
                 .spawn((
-                    DestructibleDemo,
+                    DemoScoped(DemoState::Destructible),
                     Destructible {
-                        impulse_threshold: 10.0,
+                        impulse_threshold: params.impulse_threshold,
                     },
                     RigidBody::Dynamic,
                     GravityScale(0.01),
@@ -222,7 +319,7 @@ pub fn spawn_destructible_revolute_grid(mut commands: Commands) {
                             .with_angle_limits(-1.0, 1.0)
                             .with_linear_velocity_damping(0.5)
                             .with_angular_velocity_damping(0.5)
-                            .with_compliance(0.0000001),
+                            .with_compliance(params.joint_compliance),
                     );
                 });
             }
@@ -240,7 +337,7 @@ pub fn spawn_destructible_revolute_grid(mut commands: Commands) {
                                 .with_angle_limits(-0.25, 0.25)
                                 .with_linear_velocity_damping(0.5)
                                 .with_angular_velocity_damping(0.5)
-                                .with_compliance(0.0000001),
+                                .with_compliance(params.joint_compliance),
                         );
                     });
                 }
@@ -255,7 +352,7 @@ pub fn spawn_destructible_revolute_grid(mut commands: Commands) {
                                 .with_angle_limits(-0.25, 0.25)
                                 .with_linear_velocity_damping(0.5)
                                 .with_angular_velocity_damping(0.5)
-                                .with_compliance(0.0000001),
+                                .with_compliance(params.joint_compliance),
                         );
                     });
                 }
@@ -266,10 +363,10 @@ pub fn spawn_destructible_revolute_grid(mut commands: Commands) {
     }
 }
 
-pub fn spawn_destructible_distance_grid(mut commands: Commands) {
-    let numi = 10; // number of VERTICAL hexagons (num rows)
-    let numk = 10; // number of HORIZONTAL hexagons (num columns)
-    let start_pos = Vec2::new(320.0, -35.0); // position of bottom left hexagon
+pub fn spawn_destructible_distance_grid(mut commands: Commands, params: Res<DestructibleParams>) {
+    let numi = scaled_count(10, params.scale); // number of VERTICAL hexagons (num rows)
+    let numk = scaled_count(10, params.scale); // number of HORIZONTAL hexagons (num columns)
+    let start_pos = Vec2::new(320.0 * params.scale, -35.0); // position of bottom left hexagon
 
     let mut body_entities = Vec::new();
 
@@ -289,9 +386,9 @@ pub fn spawn_destructible_distance_grid(mut commands: Commands) {
             // hexagon
             let child_entity = commands
                 .spawn((
-                    DestructibleDemo,
+                    DemoScoped(DemoState::Destructible),
                     Destructible {
-                        impulse_threshold: 10.0,
+                        impulse_threshold: params.impulse_threshold,
                     },
                     RigidBody::Dynamic,
                     GravityScale(0.01),
@@ -318,7 +415,7 @@ pub fn spawn_destructible_distance_grid(mut commands: Commands) {
                         DistanceJoint::new(parent_entity, child_entity)
                             .with_rest_length(rest_length)
                             .with_limits(rest_length - 0.1, rest_length + 2.0)
-                            .with_compliance(0.0000001),
+                            .with_compliance(params.joint_compliance),
                     );
                 });
             }
@@ -332,7 +429,7 @@ pub fn spawn_destructible_distance_grid(mut commands: Commands) {
                         DistanceJoint::new(parent_entity, child_entity)
                             .with_rest_length(HEX_WIDTH)
                             .with_limits(HEX_WIDTH - 0.1, HEX_WIDTH + 2.0)
-                            .with_compliance(0.0000001),
+                            .with_compliance(params.joint_compliance),
                     );
                 });
             }
@@ -342,10 +439,10 @@ pub fn spawn_destructible_distance_grid(mut commands: Commands) {
     }
 }
 
-pub fn spawn_destructible_static_grid(mut commands: Commands) {
-    let numi = 10; // number of VERTICAL hexagons (num rows)
-    let numk = 10; // number of HORIZONTAL hexagons (num columns)
-    let start_pos = Vec2::new(-150.0, -35.0); // position of top left hexagon
+pub fn spawn_destructible_static_grid(mut commands: Commands, params: Res<DestructibleParams>) {
+    let numi = scaled_count(10, params.scale); // number of VERTICAL hexagons (num rows)
+    let numk = scaled_count(10, params.scale); // number of HORIZONTAL hexagons (num columns)
+    let start_pos = Vec2::new(-150.0 * params.scale, -35.0); // position of top left hexagon
 
     let mut body_entities = Vec::new();
 
@@ -365,9 +462,9 @@ pub fn spawn_destructible_static_grid(mut commands: Commands) {
             // hexagon
             let child_entity = commands
                 .spawn((
-                    DestructibleDemo,
+                    DemoScoped(DemoState::Destructible),
                     Destructible {
-                        impulse_threshold: 10.0,
+                        impulse_threshold: params.impulse_threshold,
                     },
                     RigidBody::Static,
                     Collider::regular_polygon(HEX_RADIUS - 0.75, 6),
@@ -388,13 +485,79 @@ pub fn spawn_destructible_static_grid(mut commands: Commands) {
     }
 }
 
-pub fn despawn_destructible_demo(
+pub fn break_destructible_joints(
     mut commands: Commands,
-    demo_query: Query<Entity, With<DestructibleDemo>>,
+    time: Res<Time<Physics>>,
+    prismatic_query: Query<(Entity, &PrismaticJoint, &Parent)>,
+    revolute_query: Query<(Entity, &RevoluteJoint, &Parent)>,
+    distance_query: Query<(Entity, &DistanceJoint, &Parent)>,
+    destructible_query: Query<&Destructible>,
+    mut write_joint_broken: EventWriter<DestructibleJointBroken>,
 ) {
-    for demo_entity in demo_query.iter() {
-        commands.entity(demo_entity).despawn_recursive();
+    // joints report the force they applied during the last physics step,
+    // multiply by the step duration to compare against the impulse threshold
+    let delta_secs = time.delta_seconds();
+    if delta_secs <= 0.0 {
+        return;
+    }
+
+    // every joint is spawned as a child of the hexagon that owns it (entity2)
+    let mut break_joint = |joint: Entity,
+                           entities: [Entity; 2],
+                           force: Vec2,
+                           owner: &Parent,
+                           kind: DestructibleJointKind| {
+        let Ok(destructible) = destructible_query.get(owner.get()) else {
+            return;
+        };
+
+        let impulse = force.length() * delta_secs;
+        if impulse > destructible.impulse_threshold {
+            commands.entity(joint).despawn_recursive();
+            write_joint_broken.send(DestructibleJointBroken {
+                joint,
+                entity1: entities[0],
+                entity2: entities[1],
+                kind,
+                impulse,
+            });
+        }
+    };
+
+    for (joint_ent, joint, owner) in prismatic_query.iter() {
+        break_joint(
+            joint_ent,
+            [joint.entity1, joint.entity2],
+            joint.force,
+            owner,
+            DestructibleJointKind::Prismatic,
+        );
+    }
+    for (joint_ent, joint, owner) in revolute_query.iter() {
+        break_joint(
+            joint_ent,
+            [joint.entity1, joint.entity2],
+            joint.force,
+            owner,
+            DestructibleJointKind::Revolute,
+        );
+    }
+    for (joint_ent, joint, owner) in distance_query.iter() {
+        break_joint(
+            joint_ent,
+            [joint.entity1, joint.entity2],
+            joint.force,
+            owner,
+            DestructibleJointKind::Distance,
+        );
     }
 }
 
-pub fn break_destructible_joints() {}
+pub fn sync_impulse_thresholds(
+    params: Res<DestructibleParams>,
+    mut destructible_query: Query<&mut Destructible>,
+) {
+    for mut destructible in destructible_query.iter_mut() {
+        destructible.impulse_threshold = params.impulse_threshold;
+    }
+}
@@ -1,19 +1,74 @@
-use bevy::prelude::*;
+use crate::game::{
+    demo_state::{Demo, DemoScoped, DemoState},
+    physics_demos::scaled_count,
+    tuning::TuningAppExt,
+};
+
+use bevy::{ecs::schedule::SystemConfigs, prelude::*};
+use bevy_inspector_egui::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 
-// components ------------------------------------------------------------------
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Applied on reset
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct JointParams {
+    /// compliance of the anchored prismatic, revolute and distance joints
+    #[inspector(min = 0.0)]
+    pub anchor_compliance: f32,
+    /// compliance of the springy distance joint
+    #[inspector(min = 0.0)]
+    pub spring_compliance: f32,
+    /// compliance of the joints linking rope segments
+    #[inspector(min = 0.0)]
+    pub rope_compliance: f32,
+    /// number of bodies in each rope
+    #[inspector(min = 2)]
+    pub rope_links: usize,
+    /// multiplies `rope_links`, 10 gives 200-link ropes
+    #[inspector(min = 0.1, max = 100.0)]
+    pub scale: f32,
+}
+
+impl Default for JointParams {
+    fn default() -> Self {
+        Self {
+            anchor_compliance: 0.0000001,
+            spring_compliance: 0.001,
+            rope_compliance: 0.000001,
+            rope_links: 20,
+            scale: 1.0,
+        }
+    }
+}
+
+// demo ------------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Component)]
 pub struct JointDemo;
 
+impl Demo for JointDemo {
+    const STATE: DemoState = DemoState::Joint;
+    const NAME: &'static str = "Joint";
+    const DESCRIPTION: &'static str = "Each joint type, plus springs and ropes";
+
+    fn spawn_systems() -> SystemConfigs {
+        spawn_joint_demo.into_configs()
+    }
+
+    fn build(app: &mut App) {
+        app.add_tuning::<JointParams>(Some(Self::STATE));
+    }
+}
+
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-pub fn spawn_joint_demo(mut commands: Commands) {
+pub fn spawn_joint_demo(mut commands: Commands, params: Res<JointParams>) {
     // anchors -----------------------------------------------------------------
     let mut anchor_spawn = |x: f32, y: f32| {
         commands
             .spawn((
-                JointDemo,
+                DemoScoped(DemoState::Joint),
                 RigidBody::Static,
                 Collider::rectangle(5.0, 5.0),
                 TransformBundle::from_transform(Transform::from_xyz(x, y, 0.0)),
@@ -38,7 +93,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
     let mut object_spawn = |x: f32, y: f32, color: Color| {
         commands
             .spawn((
-                JointDemo,
+                DemoScoped(DemoState::Joint),
                 RigidBody::Dynamic,
                 Collider::circle(5.0),
                 TransformBundle::from_transform(Transform::from_xyz(x, y, 0.0)),
@@ -67,7 +122,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                 .with_local_anchor_1(Vec2::new(0.0, -20.0))
                 .with_free_axis(Vec2::X)
                 .with_limits(-10.0, 10.0)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -78,7 +133,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                 .with_local_anchor_2(Vec2::new(0.0, 10.0))
                 .with_free_axis(Vec2::X)
                 .with_limits(-10.0, 10.0)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -89,7 +144,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                 .with_local_anchor_2(Vec2::new(0.0, 10.0))
                 .with_free_axis(Vec2::new(0.5, 0.5))
                 .with_limits(-10.0, 10.0)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -98,7 +153,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
             RevoluteJoint::new(revolute_anchor, revolute_object)
                 .with_local_anchor_2(Vec2::new(0.0, 20.0))
                 .with_angular_velocity_damping(0.5)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -108,7 +163,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                 .with_local_anchor_1(Vec2::new(0.0, -10.0))
                 .with_local_anchor_2(Vec2::new(0.0, 10.0))
                 .with_angular_velocity_damping(0.5)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -118,7 +173,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                 .with_local_anchor_2(Vec2::new(0.0, 20.0))
                 .with_angle_limits(-0.5, 0.5)
                 .with_angular_velocity_damping(0.5)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -127,7 +182,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
             DistanceJoint::new(distance_anchor, distance_object)
                 .with_rest_length(20.0)
                 .with_limits(0.0, 20.0)
-                .with_compliance(0.0000001),
+                .with_compliance(params.anchor_compliance),
         );
     });
 
@@ -136,12 +191,12 @@ pub fn spawn_joint_demo(mut commands: Commands) {
             DistanceJoint::new(distance_anchor_2, distance_object_2)
                 .with_rest_length(20.0)
                 .with_limits(18.0, 22.0)
-                .with_compliance(0.001),
+                .with_compliance(params.spring_compliance),
         );
     });
 
     // revolute rope -----------------------------------------------------------
-    let numi = 20; // number of bodies connected by joints
+    let numi = scaled_count(params.rope_links, params.scale).max(2); // number of bodies connected by joints
     let spacing = 5.0;
     let mut start_pos = Vec2::new(50.0, -20.0); // position of top body in rope
 
@@ -158,7 +213,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
 
         let child_entity = commands
             .spawn((
-                JointDemo,
+                DemoScoped(DemoState::Joint),
                 rigid_body,
                 Collider::circle(1.5),
                 TransformBundle::from_transform(Transform::from_xyz(
@@ -182,7 +237,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                     RevoluteJoint::new(parent_entity, child_entity)
                         .with_local_anchor_2(Vec2::new(0.0, spacing))
                         .with_angle_limits(-0.25 - fi * 0.1, 0.25 + fi * 0.1)
-                        .with_compliance(0.000001),
+                        .with_compliance(params.rope_compliance),
                 );
             });
         }
@@ -206,7 +261,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
 
         let child_entity = commands
             .spawn((
-                JointDemo,
+                DemoScoped(DemoState::Joint),
                 rigid_body,
                 Collider::circle(1.5),
                 TransformBundle::from_transform(Transform::from_xyz(
@@ -230,7 +285,7 @@ pub fn spawn_joint_demo(mut commands: Commands) {
                     DistanceJoint::new(parent_entity, child_entity)
                         .with_rest_length(spacing)
                         .with_limits(0.5, spacing)
-                        .with_compliance(0.000001),
+                        .with_compliance(params.rope_compliance),
                 );
             });
         }
@@ -238,9 +293,3 @@ pub fn spawn_joint_demo(mut commands: Commands) {
         body_entities.push(child_entity);
     }
 }
-
-pub fn despawn_joint_demo(mut commands: Commands, demo_query: Query<Entity, With<JointDemo>>) {
-    for demo_entity in demo_query.iter() {
-        commands.entity(demo_entity).despawn_recursive();
-    }
-}
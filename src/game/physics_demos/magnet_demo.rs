@@ -1,7 +1,15 @@
-use crate::game::player::Player;
+use crate::game::{
+    demo_state::{Demo, DemoGeneration, DemoScoped, DemoState},
+    level::spawn_pending_level,
+    physics_demos::{grow_static_bodies, scaled_count},
+    player::Player,
+    tuning::TuningAppExt,
+};
 
-use bevy::{prelude::*, utils::Duration};
+use bevy::{ecs::schedule::SystemConfigs, prelude::*, utils::Duration};
+use bevy_inspector_egui::prelude::*;
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 // polarity conventions --------------------------------------------------------
 // -----------------------------------------------------------------------------
@@ -16,33 +24,104 @@ const NEUT_POL_COEFF: f32 = 0.0;
 
 const MAGNET_SENSOR_COL: Color = Color::rgba(1.0, 0.65, 0.0, 0.05);
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum MagnetPolarity {
     Positive,
     Negative,
     Neutral,
 }
 
-// components ------------------------------------------------------------------
+// resources -------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-#[derive(Component)]
-pub struct MagnetDemo;
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct MagnetParams {
+    /// charge of the large oscillating magnet, applied live
+    #[inspector(min = 0.0)]
+    pub center_charge: f32,
+    /// charge of every small magnet, applied live
+    #[inspector(min = 0.0)]
+    pub small_charge: f32,
+    /// seconds between polarity changes of the large magnet, applied on reset
+    #[inspector(min = 0.1)]
+    pub oscillation_period: f32,
+    /// multiplies the number of small magnets, the barrier grows to fit them, applied on reset
+    #[inspector(min = 0.1, max = 100.0)]
+    pub scale: f32,
+}
+
+impl Default for MagnetParams {
+    fn default() -> Self {
+        Self {
+            center_charge: 10_000.0,
+            small_charge: 15.0,
+            oscillation_period: 10.0,
+            scale: 1.0,
+        }
+    }
+}
 
-#[derive(Component)]
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Magnet {
     // abs_charge should always be >= 0.0
     pub abs_charge: f32,
     pub polarity: MagnetPolarity,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct OscillatingMagnet {
     pub osc_timer: Timer,
 }
 
+// demo ------------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct MagnetDemo;
+
+impl Demo for MagnetDemo {
+    const STATE: DemoState = DemoState::Magnet;
+    const NAME: &'static str = "Magnet";
+    const DESCRIPTION: &'static str = "Charged bodies attracting and repelling each other";
+    const LEVEL: Option<&'static str> = Some("levels/magnet.level.ron");
+
+    fn spawn_systems() -> SystemConfigs {
+        spawn_magnet_demo.into_configs()
+    }
+
+    fn update_systems() -> Option<SystemConfigs> {
+        Some(
+            (
+                fit_barrier_to_scale.after(spawn_pending_level),
+                apply_magnet_forces,
+                sync_magnet_charges.run_if(resource_changed::<MagnetParams>),
+            )
+                .into_configs(),
+        )
+    }
+
+    fn physics_systems() -> Option<SystemConfigs> {
+        Some(toggle_oscillating_magnets.into_configs())
+    }
+
+    fn build(app: &mut App) {
+        app.add_tuning::<MagnetParams>(Some(Self::STATE));
+    }
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// How much the barrier and the rows of magnets spread out at the given scale, so magnets
+/// keep their spacing from scale 1
+fn barrier_spread(scale: f32) -> f32 {
+    scale.sqrt().max(1.0)
+}
+
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
 pub fn spawn_magnet_demo(
     mut commands: Commands,
+    params: Res<MagnetParams>,
     mut player_query: Query<(Entity, &mut DebugRender), With<Player>>,
 ) {
     // disable player collisions for this demo, make it less visible
@@ -51,56 +130,22 @@ pub fn spawn_magnet_demo(
     player_render.axis_lengths = None;
     player_render.collider_color = Some(Color::rgba(0.5, 0.5, 0.5, 0.1));
 
-    // spawn barrier to contain dynamic magnets --------------------------------
-    commands
-        .spawn((
-            Name::new("MagnetBottomWall"),
-            MagnetDemo,
-            RigidBody::Static,
-            Collider::rectangle(200.0, 10.0),
-            Restitution::new(1.0).with_combine_rule(CoefficientCombine::Max),
-            TransformBundle::from_transform(Transform::from_xyz(0.0, -60.0, 0.0)),
-            DebugRender {
-                axis_lengths: None,
-                collider_color: Some(Color::WHITE),
-                ..default()
-            },
-        ))
-        .with_children(|children| {
-            children.spawn((
-                Name::new("MagnetLeftWall"),
-                Collider::rectangle(10.0, 130.0),
-                Restitution::new(1.0).with_combine_rule(CoefficientCombine::Max),
-                TransformBundle::from_transform(Transform::from_xyz(-105.0, 60.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-            children.spawn((
-                Name::new("MagnetRightWall"),
-                Collider::rectangle(10.0, 130.0),
-                Restitution::new(1.0).with_combine_rule(CoefficientCombine::Max),
-                TransformBundle::from_transform(Transform::from_xyz(105.0, 60.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-            children.spawn((
-                Name::new("MagnetTopWall"),
-                Collider::rectangle(200.0, 10.0),
-                Restitution::new(1.0).with_combine_rule(CoefficientCombine::Max),
-                TransformBundle::from_transform(Transform::from_xyz(0.0, 120.0, 0.0)),
-                DebugRender::default().with_collider_color(Color::WHITE),
-            ));
-        });
+    // the barrier containing the dynamic magnets comes from the level file
 
     // spawn a large, oscillating-charge magnet in the center ------------------
     commands
         .spawn((
             Name::new("MagnetCenter"),
-            MagnetDemo,
+            DemoScoped(DemoState::Magnet),
             Magnet {
-                abs_charge: 10_000.0,
+                abs_charge: params.center_charge,
                 polarity: MagnetPolarity::Neutral,
             },
             OscillatingMagnet {
-                osc_timer: Timer::new(Duration::from_secs(10), TimerMode::Repeating),
+                osc_timer: Timer::new(
+                    Duration::from_secs_f32(params.oscillation_period),
+                    TimerMode::Repeating,
+                ),
             },
             RigidBody::Dynamic,
             LockedAxes::ALL_LOCKED,
@@ -126,7 +171,7 @@ pub fn spawn_magnet_demo(
             commands
                 .spawn((
                     Name::new("MagnetSmall"),
-                    MagnetDemo,
+                    DemoScoped(DemoState::Magnet),
                     Magnet {
                         abs_charge: charge,
                         polarity,
@@ -153,30 +198,54 @@ pub fn spawn_magnet_demo(
                 });
         };
 
-    for x in (-88..89).step_by(8) {
-        magnet_small(
-            x as f32,                 // x coord
-            30.0,                     // y coord
-            15.0,                     // abs_charge
-            MagnetPolarity::Negative, // polarity
-            NEG_POL_COLOR,            // debug render color
-        );
-    }
-    for x in (-88..89).step_by(8) {
-        magnet_small(
-            x as f32,
-            -30.0,
-            15.0,
-            MagnetPolarity::Positive,
-            POS_POL_COLOR,
-        );
+    // a row of each polarity, 23 magnets wide at scale 1. Scaling grows the columns and
+    // rows evenly, rows stack away from the center inside the grown barrier
+    let spread = barrier_spread(params.scale);
+    let count = scaled_count(23, params.scale);
+    let rows = ((count as f32 / 23.0).sqrt().round() as usize).max(1);
+    let columns = count.div_ceil(rows);
+    let column_spacing = if columns > 1 {
+        176.0 * spread / (columns - 1) as f32
+    } else {
+        0.0
+    };
+    let row_spacing = 8.0;
+
+    for (y_sign, polarity, color) in [
+        (1.0, MagnetPolarity::Negative, NEG_POL_COLOR),
+        (-1.0, MagnetPolarity::Positive, POS_POL_COLOR),
+    ] {
+        for n in 0..count {
+            let (row, column) = (n / columns, n % columns);
+            magnet_small(
+                -88.0 * spread + column as f32 * column_spacing, // x coord
+                y_sign * (30.0 * spread + row as f32 * row_spacing), // y coord
+                params.small_charge,                             // abs_charge
+                polarity,                                        // polarity
+                color,                                           // debug render color
+            );
+        }
     }
 }
 
-pub fn despawn_magnet_demo(mut commands: Commands, demo_query: Query<Entity, With<MagnetDemo>>) {
-    for demo_entity in demo_query.iter() {
-        commands.entity(demo_entity).despawn_recursive();
+/// Grows the level's barrier with the scale once it has spawned, once per demo entry
+pub fn fit_barrier_to_scale(world: &mut World, mut fitted: Local<Option<DemoGeneration>>) {
+    let generation = *world.resource::<DemoGeneration>();
+    if *fitted == Some(generation) {
+        return;
+    }
+    // the level hasn't spawned yet
+    let barrier_spawned = world
+        .query_filtered::<&RigidBody, With<DemoScoped>>()
+        .iter(world)
+        .any(|rigid_body| rigid_body.is_static());
+    if !barrier_spawned {
+        return;
     }
+    *fitted = Some(generation);
+
+    let spread = barrier_spread(world.resource::<MagnetParams>().scale);
+    grow_static_bodies(world, spread);
 }
 
 pub fn apply_magnet_forces(
@@ -230,7 +299,7 @@ pub fn apply_magnet_forces(
 }
 
 pub fn toggle_oscillating_magnets(
-    time: Res<Time>,
+    time: Res<Time<Physics>>,
     mut magnet_query: Query<(&mut OscillatingMagnet, &mut Magnet, &mut DebugRender)>,
 ) {
     for (mut oscillator, mut magnet, mut render) in magnet_query.iter_mut() {
@@ -255,3 +324,16 @@ pub fn toggle_oscillating_magnets(
         }
     }
 }
+
+pub fn sync_magnet_charges(
+    params: Res<MagnetParams>,
+    mut magnet_query: Query<(&mut Magnet, Has<OscillatingMagnet>)>,
+) {
+    for (mut magnet, is_center) in magnet_query.iter_mut() {
+        magnet.abs_charge = if is_center {
+            params.center_charge
+        } else {
+            params.small_charge
+        };
+    }
+}
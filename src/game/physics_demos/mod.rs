@@ -1,7 +1,57 @@
-mod colliders_demo;
-mod conveyor_belt_demo;
+pub mod colliders_demo;
+pub mod conveyor_belt_demo;
 pub mod demo_plugin;
-mod destructible_demo;
-mod home_demo;
-mod joint_demo;
-mod magnet_demo;
+pub mod destructible_demo;
+pub mod home_demo;
+pub mod joint_demo;
+pub mod magnet_demo;
+
+use crate::game::{demo_state::DemoScoped, snapshot::ShapeSnapshot};
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// `base` entities multiplied by a demo's stress-test `scale`, never fewer than one
+pub fn scaled_count(base: usize, scale: f32) -> usize {
+    ((base as f32 * scale).round() as usize).max(1)
+}
+
+/// Grows the level's static bodies, and the colliders under them, by `factor` about the
+/// origin so a scaled-up demo has room for its extra bodies. Run it right after the level
+/// spawns, before physics has picked up the transforms.
+pub fn grow_static_bodies(world: &mut World, factor: f32) {
+    if factor <= 1.0 {
+        return;
+    }
+    let mut entities: Vec<Entity> = world
+        .query_filtered::<(Entity, &RigidBody), (With<DemoScoped>, Without<Parent>)>()
+        .iter(world)
+        .filter(|(_, rigid_body)| rigid_body.is_static())
+        .map(|(entity, _)| entity)
+        .collect();
+    let mut i = 0;
+    while i < entities.len() {
+        if let Some(children) = world.get::<Children>(entities[i]) {
+            entities.extend(children.iter().copied());
+        }
+        i += 1;
+    }
+
+    for entity in entities {
+        let mut entity_mut = world.entity_mut(entity);
+        // children are relative to their parent, so scaling every translation scales the whole
+        if let Some(mut transform) = entity_mut.get_mut::<Transform>() {
+            let translation = transform.translation.xy() * factor;
+            transform.translation = translation.extend(transform.translation.z);
+        }
+        let grown = entity_mut
+            .get::<Collider>()
+            .and_then(ShapeSnapshot::from_collider)
+            .and_then(|shape| shape.scaled(factor).to_collider());
+        if let Some(collider) = grown {
+            entity_mut.insert(collider);
+        }
+    }
+}
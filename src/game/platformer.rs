@@ -0,0 +1,301 @@
+use super::{
+    one_way_platform::PassThroughOneWayPlatform,
+    player::{Player, PlayerAction, PlayerControllerMode, DRONE_LINEAR_DAMPING},
+    replay::ReplaySession,
+    tuning::TuningAppExt,
+};
+
+use bevy::prelude::*;
+use bevy_inspector_egui::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// The player's walk-and-jump controller, used while `PlayerControllerMode::Platformer` is on
+pub struct PlatformerPlugin;
+
+impl Plugin for PlatformerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<PlayerControllerMode>()
+            // tuning ----------------------------------------------------------
+            .add_tuning::<PlatformerParams>(None)
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    toggle_controller_mode,
+                    apply_controller_mode,
+                    buffer_jump_input.run_if(resource_equals(PlayerControllerMode::Platformer)),
+                )
+                    .chain(),
+            )
+            // PhysicsSchedule -------------------------------------------------
+            // steps with physics, so pausing and time scale apply
+            .add_systems(
+                PhysicsSchedule,
+                (update_grounded, platformer_movement)
+                    .chain()
+                    .run_if(resource_equals(PlayerControllerMode::Platformer))
+                    .before(PhysicsStepSet::BroadPhase),
+            );
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct PlatformerParams {
+    /// multiplies the lab's gravity, applied live
+    #[inspector(min = 0.0, max = 20.0)]
+    pub gravity_scale: f32,
+    #[inspector(min = 0.0)]
+    pub max_speed: f32,
+    #[inspector(min = 0.0)]
+    pub ground_acceleration: f32,
+    #[inspector(min = 0.0)]
+    pub air_acceleration: f32,
+    /// upward speed a jump starts with
+    #[inspector(min = 0.0)]
+    pub jump_speed: f32,
+    /// fraction of the upward speed kept when jump is released early
+    #[inspector(min = 0.0, max = 1.0)]
+    pub jump_cut: f32,
+    /// seconds after leaving the ground that a jump is still allowed
+    #[inspector(min = 0.0, max = 1.0)]
+    pub coyote_time: f32,
+    /// degrees, steeper ground can't be stood or jumped on
+    #[inspector(min = 0.0, max = 90.0)]
+    pub max_slope_angle: f32,
+}
+
+impl Default for PlatformerParams {
+    fn default() -> Self {
+        Self {
+            gravity_scale: 3.0,
+            max_speed: 120.0,
+            ground_acceleration: 900.0,
+            air_acceleration: 450.0,
+            jump_speed: 150.0,
+            jump_cut: 0.4,
+            coyote_time: 0.1,
+            max_slope_angle: 45.0,
+        }
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component, Default)]
+pub struct PlatformerState {
+    pub grounded: bool,
+    /// seconds since the player last stood on walkable ground
+    pub air_time: f32,
+    /// velocity of the body stood on, walking and jumping are relative to it
+    pub ground_velocity: Vec2,
+    /// rising from a jump whose button is still held
+    pub jumping: bool,
+    /// jump was pressed since the last physics step
+    pub jump_requested: bool,
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+fn move_towards(current: f32, target: f32, max_delta: f32) -> f32 {
+    current + (target - current).clamp(-max_delta, max_delta)
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Locked while recording or replaying, recordings hold the mode they were made in
+pub fn toggle_controller_mode(
+    mut mode: ResMut<PlayerControllerMode>,
+    session: Res<ReplaySession>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+    if action_state.just_pressed(&PlayerAction::ToggleController) {
+        if !session.is_idle() {
+            warn!("the player controller can't change while recording or replaying");
+            return;
+        }
+        *mode = match *mode {
+            PlayerControllerMode::Drone => PlayerControllerMode::Platformer,
+            PlayerControllerMode::Platformer => PlayerControllerMode::Drone,
+        };
+        info!("player controller: {:?}", *mode);
+    }
+}
+
+/// Sets up the player's body for the current mode, on spawn and whenever the mode or the
+/// platformer params change
+pub fn apply_controller_mode(
+    mut commands: Commands,
+    mode: Res<PlayerControllerMode>,
+    params: Res<PlatformerParams>,
+    mut player_query: Query<(
+        Entity,
+        Ref<Player>,
+        &mut GravityScale,
+        &mut LinearDamping,
+        Option<&mut Rotation>,
+        Option<&mut AngularVelocity>,
+    )>,
+) {
+    for (entity, player, mut gravity_scale, mut linear_damping, rotation, angular_velocity) in
+        player_query.iter_mut()
+    {
+        if !mode.is_changed() && !params.is_changed() && !player.is_added() {
+            continue;
+        }
+
+        match *mode {
+            PlayerControllerMode::Drone => {
+                gravity_scale.0 = 0.0;
+                linear_damping.0 = DRONE_LINEAR_DAMPING;
+                commands
+                    .entity(entity)
+                    .remove::<(LockedAxes, SleepingDisabled)>()
+                    .insert(Friction::default());
+            }
+            PlayerControllerMode::Platformer => {
+                gravity_scale.0 = params.gravity_scale;
+                linear_damping.0 = 0.0;
+                // stand upright, physics fills these in after spawn so they may not be here yet
+                if let Some(mut rotation) = rotation {
+                    *rotation = Rotation::default();
+                }
+                if let Some(mut angular_velocity) = angular_velocity {
+                    angular_velocity.0 = 0.0;
+                }
+                // walking sets the speed directly, friction would only drag
+                commands.entity(entity).insert((
+                    LockedAxes::ROTATION_LOCKED,
+                    SleepingDisabled,
+                    Friction::ZERO.with_combine_rule(CoefficientCombine::Min),
+                ));
+            }
+        }
+    }
+}
+
+/// Catches jump presses between physics steps, a frame may run none or several of them
+pub fn buffer_jump_input(
+    mut player_query: Query<(&ActionState<PlayerAction>, &mut PlatformerState), With<Player>>,
+) {
+    for (action_state, mut state) in player_query.iter_mut() {
+        if action_state.just_pressed(&PlayerAction::Jump) {
+            state.jump_requested = true;
+        }
+    }
+}
+
+/// Looks for walkable ground along gravity, run every physics step
+pub fn update_grounded(
+    time: Res<Time<Physics>>,
+    gravity: Res<Gravity>,
+    params: Res<PlatformerParams>,
+    mut player_query: Query<
+        (
+            &ShapeHits,
+            &mut ShapeCaster,
+            &PassThroughOneWayPlatform,
+            &mut PlatformerState,
+        ),
+        With<Player>,
+    >,
+    ground_query: Query<Option<&LinearVelocity>, Without<Sensor>>,
+) {
+    let up = (-gravity.0).try_normalize().unwrap_or(Vec2::Y);
+    let min_alignment = params.max_slope_angle.to_radians().cos();
+
+    for (hits, mut caster, pass_through, mut state) in player_query.iter_mut() {
+        // the player is held upright, so casting in local space is casting in world space
+        let down = Direction2d::new(-up).unwrap_or(Direction2d::NEG_Y);
+        if caster.direction != down {
+            caster.direction = down;
+        }
+
+        // dropping through a platform isn't standing on it, and hits at zero distance started
+        // inside the other collider, e.g. while rising through a one-way platform
+        let ground = hits
+            .iter()
+            .filter(|_| *pass_through != PassThroughOneWayPlatform::Always)
+            .filter(|hit| hit.time_of_impact > 0.0 && hit.normal1.dot(up) >= min_alignment)
+            .find_map(|hit| ground_query.get(hit.entity).ok());
+
+        state.grounded = ground.is_some();
+        state.ground_velocity = ground.flatten().map_or(Vec2::ZERO, |velocity| velocity.0);
+        if state.grounded {
+            state.air_time = 0.0;
+        } else {
+            state.air_time += time.delta_seconds();
+        }
+    }
+}
+
+/// Walking and jumping relative to gravity, run every physics step
+pub fn platformer_movement(
+    time: Res<Time<Physics>>,
+    gravity: Res<Gravity>,
+    params: Res<PlatformerParams>,
+    mut player_query: Query<
+        (
+            &ActionState<PlayerAction>,
+            &mut PlatformerState,
+            &mut LinearVelocity,
+        ),
+        With<Player>,
+    >,
+) {
+    let delta_secs = time.delta_seconds();
+    let up = (-gravity.0).try_normalize().unwrap_or(Vec2::Y);
+    let right = -up.perp();
+
+    for (action_state, mut state, mut velocity) in player_query.iter_mut() {
+        let input = action_state
+            .clamped_axis_pair(&PlayerAction::Move)
+            .map_or(Vec2::ZERO, |axis_pair| axis_pair.xy());
+        let jump_requested = std::mem::take(&mut state.jump_requested);
+
+        // walk relative to the ground, keep momentum in the air unless steering
+        let along = velocity.dot(right);
+        let walked = if state.grounded {
+            let target = state.ground_velocity.dot(right) + input.x * params.max_speed;
+            move_towards(along, target, params.ground_acceleration * delta_secs)
+        } else if input.x != 0.0 {
+            move_towards(
+                along,
+                input.x * params.max_speed,
+                params.air_acceleration * delta_secs,
+            )
+        } else {
+            along
+        };
+        velocity.0 += right * (walked - along);
+
+        // down + jump drops through one-way platforms instead
+        let dropping = input.y < -0.5;
+        if jump_requested && !dropping && state.air_time <= params.coyote_time {
+            let rising = velocity.dot(up);
+            velocity.0 += up * (state.ground_velocity.dot(up) + params.jump_speed - rising);
+            // one jump per stay on the ground
+            state.air_time = f32::INFINITY;
+            state.jumping = true;
+        }
+
+        // letting go early cuts the jump short
+        let rising = velocity.dot(up);
+        if state.jumping && (!action_state.pressed(&PlayerAction::Jump) || rising <= 0.0) {
+            if rising > 0.0 {
+                velocity.0 -= up * rising * (1.0 - params.jump_cut);
+            }
+            state.jumping = false;
+        }
+    }
+}
@@ -1,33 +1,175 @@
-use crate::game::demo_state::EditDemoState;
+use crate::game::{
+    bindings::PlayerBindings,
+    demo_state::{DemoScoped, DemoState, EditDemoState},
+    one_way_platform::PassThroughOneWayPlatform,
+    platformer::PlatformerState,
+};
 
 use bevy::prelude::*;
+use bevy_inspector_egui::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 // -----------------------------------------------------------------------------
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
 pub enum PlayerAction {
     Move,
     SpinCW,
     SpinCCW,
     Grab,
+    Jump,
+    Afterburner,
     NextDemo,
+    SaveSnapshot,
+    LoadSnapshot,
+    ToggleRecording,
+    StartReplay,
+    TogglePause,
+    StepPhysics,
+    CycleTimeScale,
+    ToggleRewind,
+    RewindBack,
+    RewindForward,
+    ToggleEditor,
+    ToggleFreeCamera,
+    FitCameraToDemo,
+    ShowBindings,
+    PreviousDemo,
+    RestartDemo,
+    ToggleDemoMenu,
+    ToggleController,
 }
 
 impl PlayerAction {
-    fn default_input_map() -> InputMap<Self> {
+    /// Every action, in the order the bindings screen lists them
+    pub const ALL: [Self; 25] = [
+        Self::Move,
+        Self::SpinCW,
+        Self::SpinCCW,
+        Self::Grab,
+        Self::Jump,
+        Self::Afterburner,
+        Self::NextDemo,
+        Self::SaveSnapshot,
+        Self::LoadSnapshot,
+        Self::ToggleRecording,
+        Self::StartReplay,
+        Self::TogglePause,
+        Self::StepPhysics,
+        Self::CycleTimeScale,
+        Self::ToggleRewind,
+        Self::RewindBack,
+        Self::RewindForward,
+        Self::ToggleEditor,
+        Self::ToggleFreeCamera,
+        Self::FitCameraToDemo,
+        Self::ShowBindings,
+        Self::PreviousDemo,
+        Self::RestartDemo,
+        Self::ToggleDemoMenu,
+        Self::ToggleController,
+    ];
+
+    pub fn default_input_map() -> InputMap<Self> {
         let mut input_map = InputMap::default();
 
         input_map.insert(Self::Move, VirtualDPad::wasd());
         input_map.insert(Self::SpinCW, KeyCode::ArrowRight);
         input_map.insert(Self::SpinCCW, KeyCode::ArrowLeft);
         input_map.insert(Self::Grab, KeyCode::KeyE);
+        input_map.insert(Self::Jump, KeyCode::ArrowUp);
+        input_map.insert(Self::Afterburner, KeyCode::ShiftLeft);
         input_map.insert(Self::NextDemo, KeyCode::Space);
+        input_map.insert(Self::SaveSnapshot, KeyCode::F5);
+        input_map.insert(Self::LoadSnapshot, KeyCode::F9);
+        input_map.insert(Self::ToggleRecording, KeyCode::F6);
+        input_map.insert(Self::StartReplay, KeyCode::F7);
+        input_map.insert(Self::TogglePause, KeyCode::KeyP);
+        input_map.insert(Self::StepPhysics, KeyCode::Period);
+        input_map.insert(Self::CycleTimeScale, KeyCode::KeyT);
+        input_map.insert(Self::ToggleRewind, KeyCode::KeyR);
+        input_map.insert(Self::RewindBack, KeyCode::BracketLeft);
+        input_map.insert(Self::RewindForward, KeyCode::BracketRight);
+        input_map.insert(Self::ToggleEditor, KeyCode::F2);
+        input_map.insert(Self::ToggleFreeCamera, KeyCode::KeyC);
+        input_map.insert(Self::FitCameraToDemo, KeyCode::KeyF);
+        input_map.insert(Self::ShowBindings, KeyCode::F1);
+        input_map.insert(Self::PreviousDemo, KeyCode::Backspace);
+        input_map.insert(Self::RestartDemo, KeyCode::Enter);
+        input_map.insert(Self::ToggleDemoMenu, KeyCode::Tab);
+        input_map.insert(Self::ToggleController, KeyCode::KeyG);
+
+        // gamepad -------------------------------------------------------------
+        input_map.insert(Self::Move, DualAxis::left_stick());
+        input_map.insert(Self::SpinCW, GamepadButtonType::RightTrigger2);
+        input_map.insert(Self::SpinCCW, GamepadButtonType::LeftTrigger2);
+        input_map.insert(Self::Grab, GamepadButtonType::South);
+        input_map.insert(Self::Jump, GamepadButtonType::East);
+        input_map.insert(Self::Afterburner, GamepadButtonType::RightTrigger);
+        input_map.insert(Self::ToggleController, GamepadButtonType::North);
+        input_map.insert(Self::NextDemo, GamepadButtonType::Start);
+        input_map.insert(Self::PreviousDemo, GamepadButtonType::Select);
 
         input_map
     }
 }
 
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub const DRONE_LINEAR_DAMPING: f32 = 0.75;
+/// how far below the player's feet ground still counts as stood on
+const GROUND_CAST_DISTANCE: f32 = 1.5;
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// How the player moves, switched with `PlayerAction::ToggleController`
+#[derive(Resource, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub enum PlayerControllerMode {
+    /// Floats without gravity, pushed around by impulses
+    #[default]
+    Drone,
+    /// Walks and jumps under gravity, see `platformer`
+    Platformer,
+}
+
+/// Drone controls, applied live
+#[derive(Resource, Reflect, InspectorOptions, Clone)]
+#[reflect(Resource, InspectorOptions)]
+pub struct PlayerParams {
+    /// speed thrust stops adding to at full stick
+    #[inspector(min = 0.0)]
+    pub max_speed: f32,
+    /// thrust acceleration from rest at full stick
+    #[inspector(min = 0.0)]
+    pub acceleration: f32,
+    /// exponent on the stick magnitude, above 1 gives finer control near the center
+    #[inspector(min = 0.1, max = 5.0)]
+    pub response_curve: f32,
+    /// exponent on how close to max speed the drone is, higher keeps the thrust up for longer
+    /// before it tapers off
+    #[inspector(min = 0.1, max = 10.0)]
+    pub acceleration_falloff: f32,
+    /// multiplies max speed and acceleration while held, 1 turns it off
+    #[inspector(min = 1.0, max = 5.0)]
+    pub afterburner: f32,
+    pub spin_impulse: f32,
+}
+
+impl Default for PlayerParams {
+    fn default() -> Self {
+        Self {
+            max_speed: 150.0,
+            acceleration: 400.0,
+            response_curve: 1.5,
+            acceleration_falloff: 2.0,
+            afterburner: 2.0,
+            spin_impulse: 5000.0,
+        }
+    }
+}
+
 // components ------------------------------------------------------------------
 // -----------------------------------------------------------------------------
 #[derive(Component)]
@@ -36,76 +178,219 @@ pub struct Player;
 #[derive(Component)]
 pub struct Holder {
     pub is_holding: bool,
+    /// max distance from the player's center at which a body can be grabbed
+    pub reach: f32,
+    pub held_entity: Option<Entity>,
+    pub joint_entity: Option<Entity>,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component, Reflect)]
-pub enum PassThroughOneWayPlatform {
-    #[default]
-    /// Passes through a `OneWayPlatform` if the contact normal is in line with the platform's local-space up vector
-    ByNormal,
-    /// Always passes through a `OneWayPlatform`, temporarily set this to allow an actor to jump down through a platform
-    Always,
-    /// Never passes through a `OneWayPlatform`
-    Never,
+impl Holder {
+    pub fn new(reach: f32) -> Self {
+        Self {
+            is_holding: false,
+            reach,
+            held_entity: None,
+            joint_entity: None,
+        }
+    }
 }
 
 // systems ---------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-pub fn spawn_player(mut commands: Commands) {
-    commands.spawn((
-        Name::new("Player"),
-        Player,
-        Holder { is_holding: false },
-        InputManagerBundle::with_map(PlayerAction::default_input_map()),
-        PassThroughOneWayPlatform::ByNormal,
-        RigidBody::Dynamic,
-        Collider::capsule(5.0, 5.0),
-        GravityScale(0.0),
-        LinearDamping(0.75),
-        AngularDamping(0.35),
-        DebugRender::default().with_collider_color(Color::RED),
-    ));
-}
+pub fn spawn_player(
+    mut commands: Commands,
+    demo_state: Res<State<DemoState>>,
+    bindings: Res<PlayerBindings>,
+) {
+    let player = commands
+        .spawn((
+            Name::new("Player"),
+            Player,
+            DemoScoped(*demo_state.get()),
+            Holder::new(20.0),
+            PlatformerState::default(),
+            InputManagerBundle::with_map(bindings.0.clone()),
+            PassThroughOneWayPlatform::ByNormal,
+            RigidBody::Dynamic,
+            Collider::capsule(5.0, 5.0),
+            GravityScale(0.0),
+            LinearDamping(DRONE_LINEAR_DAMPING),
+            AngularDamping(0.35),
+            DebugRender::default().with_collider_color(Color::RED),
+        ))
+        .id();
 
-pub fn despawn_player(mut commands: Commands, player_query: Query<Entity, With<Player>>) {
-    for player_entity in player_query.iter() {
-        commands.entity(player_entity).despawn_recursive();
-    }
+    // a slightly thinner capsule cast down finds the ground under the feet only
+    commands.entity(player).insert(
+        ShapeCaster::new(
+            Collider::capsule(5.0, 4.9),
+            Vec2::ZERO,
+            0.0,
+            Direction2d::NEG_Y,
+        )
+        .with_max_time_of_impact(GROUND_CAST_DISTANCE)
+        .with_query_filter(SpatialQueryFilter::from_excluded_entities([player])),
+    );
 }
 
 pub fn handle_player_input(
+    params: Res<PlayerParams>,
+    mode: Res<PlayerControllerMode>,
     action_query: Query<&ActionState<PlayerAction>, With<Player>>,
-    mut player_query: Query<(&mut ExternalImpulse, &mut ExternalAngularImpulse), With<Player>>,
+    mut player_query: Query<&mut ExternalAngularImpulse, With<Player>>,
     mut write_edit_demo: EventWriter<EditDemoState>,
 ) {
     let action_state = action_query.single();
+    // the platformer controller drives the player itself, movement is `drone_thrust`
+    let drone = *mode == PlayerControllerMode::Drone;
+
+    if drone && action_state.just_pressed(&PlayerAction::SpinCW) {
+        let mut player_impulse = player_query.single_mut();
+        player_impulse.apply_impulse(-params.spin_impulse);
+    }
+    if drone && action_state.just_pressed(&PlayerAction::SpinCCW) {
+        let mut player_impulse = player_query.single_mut();
+        player_impulse.apply_impulse(params.spin_impulse);
+    }
+
+    if action_state.just_released(&PlayerAction::NextDemo) {
+        write_edit_demo.send(EditDemoState::Next);
+    };
+    if action_state.just_released(&PlayerAction::PreviousDemo) {
+        write_edit_demo.send(EditDemoState::Previous);
+    };
+    if action_state.just_released(&PlayerAction::RestartDemo) {
+        write_edit_demo.send(EditDemoState::Restart);
+    };
+}
+
+/// Hold-to-thrust drone movement, run every physics step. Thrust tapers off as the speed along
+/// the stick approaches the max speed for how far the stick is pushed.
+pub fn drone_thrust(
+    time: Res<Time<Physics>>,
+    params: Res<PlayerParams>,
+    mut player_query: Query<(&ActionState<PlayerAction>, &mut LinearVelocity), With<Player>>,
+) {
+    let delta_secs = time.delta_seconds();
+    if delta_secs <= 0.0 {
+        return;
+    }
 
-    if action_state.just_pressed(&PlayerAction::Move) {
-        let axis_pair = action_state
+    for (action_state, mut velocity) in player_query.iter_mut() {
+        let input = action_state
             .clamped_axis_pair(&PlayerAction::Move)
-            .unwrap()
-            .xy();
+            .map_or(Vec2::ZERO, |axis_pair| axis_pair.xy());
+        let magnitude = input.length().min(1.0);
+        if magnitude <= 0.0 {
+            continue;
+        }
+
+        let direction = input.normalize();
+        let throttle = magnitude.powf(params.response_curve);
+        let boost = if action_state.pressed(&PlayerAction::Afterburner) {
+            params.afterburner
+        } else {
+            1.0
+        };
+        let max_speed = params.max_speed * throttle * boost;
+        if max_speed <= 0.0 {
+            continue;
+        }
+
+        // full thrust when still or moving against the stick, none at max speed
+        let speed_ratio = (velocity.dot(direction) / max_speed).clamp(0.0, 1.0);
+        let thrust = 1.0 - speed_ratio.powf(params.acceleration_falloff);
+        velocity.0 += direction * params.acceleration * throttle * boost * thrust * delta_secs;
+    }
+}
+
+pub fn handle_grab(
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut player_query: Query<(Entity, &mut Holder, &Transform, &LinearVelocity), With<Player>>,
+    mut body_query: Query<(&RigidBody, &Transform, &mut LinearVelocity), Without<Player>>,
+) {
+    let action_state = action_query.single();
+    let (player_ent, mut holder, player_transform, player_velocity) = player_query.single_mut();
 
-        let (mut player_impulse, _) = player_query.single_mut();
-        player_impulse.apply_impulse(axis_pair * 4000.0);
+    // the held body may have been despawned out from under us (e.g. a conveyor block timing out)
+    if let Some(held_ent) = holder.held_entity {
+        if body_query.get(held_ent).is_err() {
+            if let Some(joint_ent) = holder.joint_entity.take() {
+                commands.entity(joint_ent).despawn_recursive();
+            }
+            holder.held_entity = None;
+            holder.is_holding = false;
+        }
     }
 
-    if action_state.just_pressed(&PlayerAction::SpinCW) {
-        let (_, mut player_impulse) = player_query.single_mut();
-        player_impulse.apply_impulse(-5000.0);
+    if !action_state.just_pressed(&PlayerAction::Grab) {
+        return;
     }
-    if action_state.just_pressed(&PlayerAction::SpinCCW) {
-        let (_, mut player_impulse) = player_query.single_mut();
-        player_impulse.apply_impulse(5000.0);
+
+    // release / throw ---------------------------------------------------------
+    if holder.is_holding {
+        if let Some(joint_ent) = holder.joint_entity.take() {
+            commands.entity(joint_ent).despawn_recursive();
+        }
+        if let Some(held_ent) = holder.held_entity.take() {
+            if let Ok((_, _, mut held_velocity)) = body_query.get_mut(held_ent) {
+                held_velocity.0 = player_velocity.0;
+            }
+        }
+        holder.is_holding = false;
+        return;
     }
 
-    if action_state.just_pressed(&PlayerAction::Grab) {
-        println!("Grabbing");
-    };
+    // grab the nearest dynamic body within reach ------------------------------
+    let player_pos = player_transform.translation.xy();
+    let nearest = spatial_query
+        .shape_intersections(
+            &Collider::circle(holder.reach),
+            player_pos,
+            0.0,
+            SpatialQueryFilter::from_excluded_entities([player_ent]),
+        )
+        .into_iter()
+        .filter_map(|ent| {
+            // child colliders (walls, magnet sensors) have no RigidBody and are skipped here
+            let (rigid_body, transform, _) = body_query.get(ent).ok()?;
+            rigid_body
+                .is_dynamic()
+                .then(|| (ent, transform.translation.xy().distance(player_pos)))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
 
-    if action_state.just_released(&PlayerAction::NextDemo) {
-        write_edit_demo.send(EditDemoState);
+    let Some((held_ent, _)) = nearest else {
+        return;
+    };
+    let Ok((_, held_transform, _)) = body_query.get(held_ent) else {
+        return;
     };
+
+    // keep the body where it was grabbed, relative to the player's local frame. It hangs on
+    // a pivot at its center, free to turn, rather than taking on the player's rotation
+    let offset = held_transform.translation.xy() - player_pos;
+    let local_offset = (player_transform.rotation.inverse() * offset.extend(0.0)).xy();
+
+    // spawn the joint as a child of the player so it is cleaned up with it
+    let mut joint_ent = None;
+    commands.entity(player_ent).with_children(|cmd| {
+        joint_ent = Some(
+            cmd.spawn((
+                Name::new("PlayerGrabJoint"),
+                RevoluteJoint::new(player_ent, held_ent)
+                    .with_local_anchor_1(local_offset)
+                    .with_compliance(0.00001),
+            ))
+            .id(),
+        );
+    });
+
+    holder.is_holding = true;
+    holder.held_entity = Some(held_ent);
+    holder.joint_entity = joint_ent;
 }
 
 pub fn pass_through_one_way_platform(
@@ -116,8 +401,12 @@ pub fn pass_through_one_way_platform(
     let (entity, mut pass_through_one_way_platform) = player_query.single_mut();
 
     let action_state = action_query.single();
+    let holding_down = action_state
+        .clamped_axis_pair(&PlayerAction::Move)
+        .is_some_and(|axis_pair| axis_pair.y() < -0.5);
 
-    if action_state.pressed(&PlayerAction::Grab) {
+    // down + jump drops through
+    if holding_down && action_state.pressed(&PlayerAction::Jump) {
         *pass_through_one_way_platform = PassThroughOneWayPlatform::Always;
 
         // Wake up body when it's allowed to drop down.
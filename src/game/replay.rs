@@ -0,0 +1,437 @@
+use super::{
+    demo_state::{enter_demo_now, DemoState},
+    player::{Player, PlayerAction, PlayerControllerMode},
+    tuning::{load_tuning, save_tuning},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy, utils::Duration};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::{axislike::DualAxisData, buttonlike::ButtonState, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub const DEFAULT_RECORDING_PATH: &str = "recordings/recording.ron";
+
+/// While recording or replaying, every frame advances time by exactly one tick at this rate
+pub const LOCKSTEP_HZ: f64 = 60.0;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Only gameplay actions are recorded, lab controls (snapshots, recording) are left live
+pub const RECORDED_ACTIONS: [PlayerAction; 6] = [
+    PlayerAction::Move,
+    PlayerAction::SpinCW,
+    PlayerAction::SpinCCW,
+    PlayerAction::Grab,
+    PlayerAction::Jump,
+    PlayerAction::Afterburner,
+];
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<ReplaySession>()
+            // events ----------------------------------------------------------
+            .add_event::<StartRecording>()
+            .add_event::<StopRecording>()
+            .add_event::<StartReplay>()
+            .add_event::<ReplayDiverged>()
+            // PreUpdate -------------------------------------------------------
+            .add_systems(
+                PreUpdate,
+                feed_replay_input.in_set(InputManagerSystem::ManualControl),
+            )
+            // Update ----------------------------------------------------------
+            .add_systems(Update, (handle_replay_input, stop_recording).chain())
+            // PostUpdate ------------------------------------------------------
+            .add_systems(PostUpdate, record_and_verify_tick.after(PhysicsSet::Sync))
+            // Last ------------------------------------------------------------
+            // sessions start at the very end of a frame, so the next frame is their first tick
+            .add_systems(Last, begin_sessions);
+    }
+}
+
+// recording format ------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InputRecording {
+    pub demo: DemoState,
+    pub tick_hz: f64,
+    /// every tuning parameter set as ron, by name, applied before the demo is entered
+    #[serde(default)]
+    pub tuning: BTreeMap<String, String>,
+    #[serde(default)]
+    pub controller_mode: PlayerControllerMode,
+    pub ticks: Vec<RecordedTick>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedTick {
+    pub pressed: Vec<PlayerAction>,
+    pub move_axis: Vec2,
+    /// `body_checksum` after this tick's physics step
+    pub checksum: u64,
+}
+
+// events ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Restarts the running demo and records from its first tick
+#[derive(Event)]
+pub struct StartRecording;
+
+#[derive(Event)]
+pub struct StopRecording {
+    pub path: PathBuf,
+}
+
+/// Enters the recording's demo from scratch and plays the recorded input back
+#[derive(Event)]
+pub struct StartReplay {
+    pub path: PathBuf,
+}
+
+#[derive(Event, Debug)]
+pub struct ReplayDiverged {
+    pub tick: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Default)]
+pub enum ReplaySession {
+    #[default]
+    Idle,
+    Recording {
+        recording: InputRecording,
+        previous_timing: PreviousTiming,
+    },
+    Replaying {
+        recording: InputRecording,
+        tick: usize,
+        diverged_at: Option<usize>,
+        previous_timing: PreviousTiming,
+    },
+}
+
+/// The time settings lockstep replaced, put back when the session ends
+pub struct PreviousTiming {
+    time_strategy: TimeUpdateStrategy,
+    timestep: TimestepMode,
+}
+
+impl ReplaySession {
+    pub fn is_idle(&self) -> bool {
+        matches!(self, Self::Idle)
+    }
+}
+
+// helpers ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// FNV-1a over the bits of `values`, fixed so that checksums saved in recordings stay valid
+/// across toolchains
+fn fnv1a(values: &[u32]) -> u64 {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// Order-independent hash of every rigid body's position and rotation
+pub fn body_checksum<'a>(bodies: impl Iterator<Item = (&'a Position, &'a Rotation)>) -> u64 {
+    bodies.fold(0, |checksum, (position, rotation)| {
+        checksum.wrapping_add(fnv1a(&[
+            position.x.to_bits(),
+            position.y.to_bits(),
+            rotation.as_radians().to_bits(),
+        ]))
+    })
+}
+
+/// Locks virtual and physics time to one fixed tick per frame, returning the settings to put
+/// back afterwards
+fn enable_lockstep(world: &mut World) -> PreviousTiming {
+    let tick = Duration::from_secs_f64(1.0 / LOCKSTEP_HZ);
+    let time_strategy = world
+        .remove_resource::<TimeUpdateStrategy>()
+        .unwrap_or_default();
+    world.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+
+    let mut physics_time = world.resource_mut::<Time<Physics>>();
+    let timestep = std::mem::replace(
+        physics_time.timestep_mode_mut(),
+        TimestepMode::FixedOnce { delta: tick },
+    );
+    PreviousTiming {
+        time_strategy,
+        timestep,
+    }
+}
+
+fn disable_lockstep(
+    time_strategy: &mut TimeUpdateStrategy,
+    physics_time: &mut Time<Physics>,
+    previous_timing: PreviousTiming,
+) {
+    *time_strategy = previous_timing.time_strategy;
+    *physics_time.timestep_mode_mut() = previous_timing.timestep;
+}
+
+pub fn write_recording(recording: &InputRecording, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let ron = ron::ser::to_string_pretty(recording, ron::ser::PrettyConfig::default())?;
+    fs::write(path, ron)?;
+    Ok(())
+}
+
+pub fn read_recording(path: &Path) -> Result<InputRecording, Box<dyn Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn handle_replay_input(
+    session: Res<ReplaySession>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut write_start_recording: EventWriter<StartRecording>,
+    mut write_stop_recording: EventWriter<StopRecording>,
+    mut write_start_replay: EventWriter<StartReplay>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+
+    if action_state.just_pressed(&PlayerAction::ToggleRecording) {
+        match *session {
+            ReplaySession::Idle => {
+                write_start_recording.send(StartRecording);
+            }
+            ReplaySession::Recording { .. } => {
+                write_stop_recording.send(StopRecording {
+                    path: DEFAULT_RECORDING_PATH.into(),
+                });
+            }
+            ReplaySession::Replaying { .. } => {}
+        }
+    }
+    if action_state.just_pressed(&PlayerAction::StartReplay) && session.is_idle() {
+        write_start_replay.send(StartReplay {
+            path: DEFAULT_RECORDING_PATH.into(),
+        });
+    }
+}
+
+pub fn begin_sessions(world: &mut World) {
+    let start_recording = world
+        .resource_mut::<Events<StartRecording>>()
+        .drain()
+        .last()
+        .is_some();
+    let start_replay = world.resource_mut::<Events<StartReplay>>().drain().last();
+
+    if !world.resource::<ReplaySession>().is_idle() {
+        return;
+    }
+
+    if let Some(StartReplay { path }) = start_replay {
+        let recording = match read_recording(&path) {
+            Ok(recording) => recording,
+            Err(err) => {
+                error!("failed to load recording from {}: {err}", path.display());
+                return;
+            }
+        };
+        if recording.tick_hz != LOCKSTEP_HZ {
+            warn!(
+                "recording was made at {} Hz but replays at {LOCKSTEP_HZ} Hz, expect divergence",
+                recording.tick_hz
+            );
+        }
+
+        // spawn-time parameters have to be in place before the demo is entered
+        load_tuning(world, &recording.tuning);
+        world.insert_resource(recording.controller_mode);
+        enter_demo_now(world, recording.demo);
+        let previous_timing = enable_lockstep(world);
+        info!(
+            "replaying {} ticks of {:?}",
+            recording.ticks.len(),
+            recording.demo
+        );
+        world.insert_resource(ReplaySession::Replaying {
+            recording,
+            tick: 0,
+            diverged_at: None,
+            previous_timing,
+        });
+    } else if start_recording {
+        let demo = *world.resource::<State<DemoState>>().get();
+
+        enter_demo_now(world, demo);
+        let previous_timing = enable_lockstep(world);
+        info!("recording {demo:?}");
+        world.insert_resource(ReplaySession::Recording {
+            recording: InputRecording {
+                demo,
+                tick_hz: LOCKSTEP_HZ,
+                tuning: save_tuning(world),
+                controller_mode: *world.resource::<PlayerControllerMode>(),
+                ticks: Vec::new(),
+            },
+            previous_timing,
+        });
+    }
+}
+
+pub fn stop_recording(
+    mut session: ResMut<ReplaySession>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut physics_time: ResMut<Time<Physics>>,
+    mut read_stop_recording: EventReader<StopRecording>,
+) {
+    let Some(StopRecording { path }) = read_stop_recording.read().last() else {
+        return;
+    };
+    if !matches!(*session, ReplaySession::Recording { .. }) {
+        return;
+    }
+    let ReplaySession::Recording {
+        recording,
+        previous_timing,
+    } = std::mem::take(&mut *session)
+    else {
+        return;
+    };
+
+    disable_lockstep(&mut time_strategy, &mut physics_time, previous_timing);
+    match write_recording(&recording, path) {
+        Ok(()) => info!(
+            "saved {} ticks of input to {}",
+            recording.ticks.len(),
+            path.display()
+        ),
+        Err(err) => error!("failed to save recording to {}: {err}", path.display()),
+    }
+}
+
+/// Overrides the player's recorded actions with the replay's, after leafwing has read live input.
+/// The button states are set outright from this tick and the one before, pressing would turn
+/// a held action into a fresh press each tick once leafwing has released it.
+pub fn feed_replay_input(
+    session: Res<ReplaySession>,
+    mut action_query: Query<&mut ActionState<PlayerAction>, With<Player>>,
+) {
+    let ReplaySession::Replaying {
+        recording, tick, ..
+    } = &*session
+    else {
+        return;
+    };
+    let Some(recorded) = recording.ticks.get(*tick) else {
+        return;
+    };
+    let previous = tick
+        .checked_sub(1)
+        .and_then(|previous| recording.ticks.get(previous));
+    let Ok(mut action_state) = action_query.get_single_mut() else {
+        return;
+    };
+
+    for action in RECORDED_ACTIONS {
+        let was_pressed = previous.is_some_and(|previous| previous.pressed.contains(&action));
+        let state = match (was_pressed, recorded.pressed.contains(&action)) {
+            (false, true) => ButtonState::JustPressed,
+            (true, true) => ButtonState::Pressed,
+            (true, false) => ButtonState::JustReleased,
+            (false, false) => ButtonState::Released,
+        };
+        action_state.action_data_mut_or_default(&action).state = state;
+    }
+
+    let move_data = action_state.action_data_mut_or_default(&PlayerAction::Move);
+    move_data.axis_pair = Some(DualAxisData::from_xy(recorded.move_axis));
+    move_data.value = recorded.move_axis.length();
+}
+
+pub fn record_and_verify_tick(
+    mut session: ResMut<ReplaySession>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut physics_time: ResMut<Time<Physics>>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    body_query: Query<(&Position, &Rotation), With<RigidBody>>,
+    mut write_diverged: EventWriter<ReplayDiverged>,
+) {
+    match &mut *session {
+        ReplaySession::Idle => {}
+        ReplaySession::Recording { recording, .. } => {
+            let Ok(action_state) = action_query.get_single() else {
+                return;
+            };
+            recording.ticks.push(RecordedTick {
+                pressed: RECORDED_ACTIONS
+                    .into_iter()
+                    .filter(|action| action_state.pressed(action))
+                    .collect(),
+                move_axis: action_state
+                    .clamped_axis_pair(&PlayerAction::Move)
+                    .map_or(Vec2::ZERO, |axis_pair| axis_pair.xy()),
+                checksum: body_checksum(body_query.iter()),
+            });
+        }
+        ReplaySession::Replaying {
+            recording,
+            tick,
+            diverged_at,
+            ..
+        } => {
+            if let Some(recorded) = recording.ticks.get(*tick) {
+                let actual = body_checksum(body_query.iter());
+                if actual != recorded.checksum && diverged_at.is_none() {
+                    warn!("replay diverged from the recording at tick {tick}");
+                    *diverged_at = Some(*tick);
+                    write_diverged.send(ReplayDiverged {
+                        tick: *tick,
+                        expected: recorded.checksum,
+                        actual,
+                    });
+                }
+                *tick += 1;
+            }
+
+            if *tick >= recording.ticks.len() {
+                match diverged_at {
+                    Some(diverged_at) => warn!("replay finished, diverged at tick {diverged_at}"),
+                    None => info!(
+                        "replay finished, all {} ticks matched",
+                        recording.ticks.len()
+                    ),
+                }
+                if let ReplaySession::Replaying {
+                    previous_timing, ..
+                } = std::mem::take(&mut *session)
+                {
+                    disable_lockstep(&mut time_strategy, &mut physics_time, previous_timing);
+                }
+            }
+        }
+    }
+}
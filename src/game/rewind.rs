@@ -0,0 +1,442 @@
+use super::{
+    demo_state::{DemoGeneration, DemoScoped},
+    player::{Player, PlayerAction},
+    replay::ReplaySession,
+};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use std::collections::VecDeque;
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// How much physics time the rewind buffer keeps
+pub const REWIND_SECONDS: f32 = 10.0;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<RewindBuffer>()
+            .init_resource::<Rewind>()
+            // Update ----------------------------------------------------------
+            .add_systems(Update, (handle_rewind_input, apply_rewind_frame).chain())
+            // PostUpdate ------------------------------------------------------
+            .add_systems(PostUpdate, record_rewind_frame.after(PhysicsSet::Sync));
+    }
+}
+
+// buffer ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Clone, Copy)]
+pub struct BodyFrame {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub rotation: f32,
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+}
+
+/// State of every body and which joints existed, right after one physics step
+pub struct RewindFrame {
+    /// physics time covered by the step that produced this frame
+    pub delta: f32,
+    pub bodies: Vec<BodyFrame>,
+    pub joints: HashSet<Entity>,
+}
+
+#[derive(Clone, Copy)]
+pub enum StoredJoint {
+    Prismatic(PrismaticJoint),
+    Revolute(RevoluteJoint),
+    Distance(DistanceJoint),
+}
+
+impl StoredJoint {
+    fn entities(&self) -> [Entity; 2] {
+        match self {
+            Self::Prismatic(joint) => [joint.entity1, joint.entity2],
+            Self::Revolute(joint) => [joint.entity1, joint.entity2],
+            Self::Distance(joint) => [joint.entity1, joint.entity2],
+        }
+    }
+}
+
+/// What's needed to respawn a joint that broke (or was removed) after a rewound frame
+pub struct RecordedJoint {
+    pub parent: Option<Entity>,
+    pub name: Option<Name>,
+    pub joint: StoredJoint,
+}
+
+#[derive(Resource, Default)]
+pub struct RewindBuffer {
+    pub frames: VecDeque<RewindFrame>,
+    /// every joint seen since the demo was entered, keyed by entity
+    pub joints: HashMap<Entity, RecordedJoint>,
+    /// physics time covered by `frames`
+    pub duration: f32,
+    generation: DemoGeneration,
+    last_physics_elapsed: f64,
+}
+
+impl RewindBuffer {
+    pub fn clear(&mut self, generation: DemoGeneration) {
+        self.frames.clear();
+        self.joints.clear();
+        self.duration = 0.0;
+        self.generation = generation;
+    }
+
+    fn push(&mut self, frame: RewindFrame) {
+        self.duration += frame.delta;
+        self.frames.push_back(frame);
+
+        while self.duration > REWIND_SECONDS && self.frames.len() > 1 {
+            let oldest = self.frames.pop_front().unwrap();
+            self.duration -= oldest.delta;
+        }
+    }
+
+    /// Physics time between the frame at `index` and the newest frame
+    pub fn age(&self, index: usize) -> f32 {
+        self.frames.iter().skip(index + 1).map(|f| f.delta).sum()
+    }
+
+    /// Drops every frame after `index`, so the simulation continues from there
+    fn truncate(&mut self, index: usize) {
+        for frame in self.frames.drain(index + 1..) {
+            self.duration -= frame.delta;
+        }
+    }
+
+    /// Swaps joint entity ids after respawning, in every frame and the joint table
+    fn remap_joints(&mut self, remap: &HashMap<Entity, Entity>) {
+        for frame in self.frames.iter_mut() {
+            frame.joints = frame
+                .joints
+                .iter()
+                .map(|joint| *remap.get(joint).unwrap_or(joint))
+                .collect();
+        }
+        for (old, new) in remap {
+            if let Some(recorded) = self.joints.remove(old) {
+                self.joints.insert(*new, recorded);
+            }
+        }
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource, Default)]
+pub enum Rewind {
+    #[default]
+    Live,
+    /// Physics is paused and the world shows the buffered frame at `cursor`
+    Scrubbing { cursor: usize, was_paused: bool },
+}
+
+impl Rewind {
+    pub fn is_live(&self) -> bool {
+        matches!(self, Self::Live)
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component)]
+pub struct RewindIndicator;
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn record_rewind_frame(
+    rewind: Res<Rewind>,
+    mut buffer: ResMut<RewindBuffer>,
+    generation: Res<DemoGeneration>,
+    physics_time: Res<Time<Physics>>,
+    body_query: Query<
+        (
+            Entity,
+            &Position,
+            &Rotation,
+            &LinearVelocity,
+            &AngularVelocity,
+        ),
+        With<RigidBody>,
+    >,
+    joint_query: Query<(
+        Entity,
+        Option<&Parent>,
+        Option<&Name>,
+        AnyOf<(&PrismaticJoint, &RevoluteJoint, &DistanceJoint)>,
+    )>,
+) {
+    if buffer.generation != *generation {
+        buffer.clear(*generation);
+    }
+
+    // only record frames in which physics actually stepped
+    let elapsed = physics_time.elapsed_seconds_f64();
+    let delta = (elapsed - buffer.last_physics_elapsed) as f32;
+    buffer.last_physics_elapsed = elapsed;
+    if delta <= 0.0 || !rewind.is_live() {
+        return;
+    }
+
+    let bodies = body_query
+        .iter()
+        .map(|(entity, position, rotation, linvel, angvel)| BodyFrame {
+            entity,
+            position: position.0,
+            rotation: rotation.as_radians(),
+            linear_velocity: linvel.0,
+            angular_velocity: angvel.0,
+        })
+        .collect();
+
+    let mut joints = HashSet::new();
+    for (entity, parent, name, (prismatic, revolute, distance)) in joint_query.iter() {
+        joints.insert(entity);
+        if buffer.joints.contains_key(&entity) {
+            continue;
+        }
+
+        let joint = match (prismatic, revolute, distance) {
+            (Some(joint), ..) => StoredJoint::Prismatic(*joint),
+            (_, Some(joint), _) => StoredJoint::Revolute(*joint),
+            (.., Some(joint)) => StoredJoint::Distance(*joint),
+            _ => continue,
+        };
+        buffer.joints.insert(
+            entity,
+            RecordedJoint {
+                parent: parent.map(Parent::get),
+                name: name.cloned(),
+                joint,
+            },
+        );
+    }
+
+    buffer.push(RewindFrame {
+        delta,
+        bodies,
+        joints,
+    });
+}
+
+pub fn handle_rewind_input(
+    mut commands: Commands,
+    mut rewind: ResMut<Rewind>,
+    mut buffer: ResMut<RewindBuffer>,
+    session: Res<ReplaySession>,
+    mut physics_time: ResMut<Time<Physics>>,
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    scoped_query: Query<Entity, (With<DemoScoped>, With<RigidBody>, Without<Parent>)>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+
+    let Rewind::Scrubbing { cursor, was_paused } = &mut *rewind else {
+        // rewinding while a recording or replay runs would break its lockstep
+        if action_state.just_pressed(&PlayerAction::ToggleRewind)
+            && session.is_idle()
+            && !buffer.frames.is_empty()
+        {
+            *rewind = Rewind::Scrubbing {
+                cursor: buffer.frames.len() - 1,
+                was_paused: physics_time.is_paused(),
+            };
+            physics_time.pause();
+        }
+        return;
+    };
+
+    // the frames were cleared underneath us, e.g. the demo changed
+    if buffer.frames.is_empty() {
+        if !*was_paused {
+            physics_time.unpause();
+        }
+        *rewind = Rewind::Live;
+        return;
+    }
+
+    // resume from the shown frame, either explicitly or by unpausing or stepping physics
+    let toggled = action_state.just_pressed(&PlayerAction::ToggleRewind);
+    if toggled || !physics_time.is_paused() || action_state.just_pressed(&PlayerAction::StepPhysics)
+    {
+        let cursor = (*cursor).min(buffer.frames.len() - 1);
+        buffer.truncate(cursor);
+
+        // bodies spawned after the resumed frame never existed at this point
+        let existed: HashSet<Entity> = buffer.frames[cursor]
+            .bodies
+            .iter()
+            .map(|body| body.entity)
+            .collect();
+        for entity in scoped_query.iter() {
+            if !existed.contains(&entity) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        if toggled && !*was_paused {
+            physics_time.unpause();
+        }
+        *rewind = Rewind::Live;
+        return;
+    }
+
+    // scrub one frame per update while held
+    if action_state.pressed(&PlayerAction::RewindBack) {
+        *cursor = cursor.saturating_sub(1);
+    }
+    if action_state.pressed(&PlayerAction::RewindForward) {
+        *cursor = (*cursor + 1).min(buffer.frames.len() - 1);
+    }
+}
+
+/// Puts bodies and joints back the way they were in the frame under the cursor
+pub fn apply_rewind_frame(
+    mut commands: Commands,
+    rewind: Res<Rewind>,
+    mut buffer: ResMut<RewindBuffer>,
+    mut body_query: Query<
+        (
+            &mut Position,
+            &mut Rotation,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+        ),
+        With<RigidBody>,
+    >,
+    joint_query: Query<
+        (),
+        Or<(
+            With<PrismaticJoint>,
+            With<RevoluteJoint>,
+            With<DistanceJoint>,
+        )>,
+    >,
+    mut shown: Local<Option<usize>>,
+) {
+    let Rewind::Scrubbing { cursor, .. } = *rewind else {
+        *shown = None;
+        return;
+    };
+    if *shown == Some(cursor) {
+        return;
+    }
+    *shown = Some(cursor);
+    let Some(frame) = buffer.frames.get(cursor) else {
+        return;
+    };
+
+    // bodies --------------------------------------------------------------
+    for body in frame.bodies.iter() {
+        // bodies despawned since (e.g. conveyor blocks) can't be brought back
+        let Ok((mut position, mut rotation, mut linvel, mut angvel)) =
+            body_query.get_mut(body.entity)
+        else {
+            continue;
+        };
+        position.0 = body.position;
+        *rotation = Rotation::from_radians(body.rotation);
+        linvel.0 = body.linear_velocity;
+        angvel.0 = body.angular_velocity;
+    }
+
+    // joints --------------------------------------------------------------
+    let mut remap = HashMap::new();
+    for (entity, recorded) in buffer.joints.iter() {
+        let existed = frame.joints.contains(entity);
+        let exists = joint_query.contains(*entity);
+
+        if exists && !existed {
+            commands.entity(*entity).despawn_recursive();
+        } else if existed && !exists {
+            // don't bring back joints whose bodies are gone
+            if recorded
+                .joint
+                .entities()
+                .iter()
+                .any(|body| !body_query.contains(*body))
+            {
+                continue;
+            }
+
+            let mut joint_cmd = match recorded.joint {
+                StoredJoint::Prismatic(joint) => commands.spawn(joint),
+                StoredJoint::Revolute(joint) => commands.spawn(joint),
+                StoredJoint::Distance(joint) => commands.spawn(joint),
+            };
+            if let Some(name) = &recorded.name {
+                joint_cmd.insert(name.clone());
+            }
+            if let Some(parent) = recorded
+                .parent
+                .filter(|parent| body_query.contains(*parent))
+            {
+                joint_cmd.set_parent(parent);
+            }
+            remap.insert(*entity, joint_cmd.id());
+        }
+    }
+    if !remap.is_empty() {
+        buffer.remap_joints(&remap);
+    }
+}
+
+pub fn spawn_rewind_indicator(mut commands: Commands) {
+    commands.spawn((
+        Name::new("RewindIndicator"),
+        RewindIndicator,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::ORANGE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(25.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_rewind_indicator(
+    rewind: Res<Rewind>,
+    buffer: Res<RewindBuffer>,
+    mut indicator_query: Query<&mut Text, With<RewindIndicator>>,
+) {
+    let Ok(mut text) = indicator_query.get_single_mut() else {
+        return;
+    };
+
+    text.sections[0].value = match *rewind {
+        Rewind::Live => String::new(),
+        Rewind::Scrubbing { cursor, .. } => {
+            let joints = buffer.frames.get(cursor).map_or(0, |f| f.joints.len());
+            format!(
+                "REWIND -{:.2}s  {}/{}  joints {joints}",
+                buffer.age(cursor),
+                cursor + 1,
+                buffer.frames.len(),
+            )
+        }
+    };
+}
@@ -0,0 +1,966 @@
+use super::{
+    demo_state::{DemoScoped, DemoState},
+    kinematic_platform::{KinematicPlatform, NoPlatformCarry},
+    one_way_platform::OneWayPlatform,
+    physics_demos::{
+        conveyor_belt_demo::{ConveyorBelt, ConveyorBeltBlockTimer},
+        destructible_demo::Destructible,
+        magnet_demo::{Magnet, OscillatingMagnet},
+    },
+    player::{Player, PlayerAction},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_2d::{
+    parry::{na::Vector2, shape::SupportMap},
+    prelude::*,
+};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    error::Error,
+    f32::consts::TAU,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub const DEFAULT_SNAPSHOT_PATH: &str = "snapshots/snapshot.ron";
+
+// custom shapes (ellipses, regular polygons) are stored as a convex hull of this many support points
+const HULL_SAMPLES: usize = 32;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // events ----------------------------------------------------------
+            .add_event::<SaveSnapshot>()
+            .add_event::<LoadSnapshot>()
+            // Update ----------------------------------------------------------
+            .add_systems(
+                Update,
+                (
+                    handle_snapshot_input,
+                    handle_save_snapshot,
+                    handle_load_snapshot,
+                    apply_pending_snapshot,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// snapshot format -------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Every physics entity of a running demo, plus the player's motion
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LabSnapshot {
+    pub demo: DemoState,
+    pub gravity: Vec2,
+    pub player: Option<BodyState>,
+    pub entities: Vec<EntitySnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BodyState {
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+}
+
+/// One entity in a snapshot. Entities refer to each other (parents, joints) by index.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct EntitySnapshot {
+    #[serde(default)]
+    pub parent: Option<usize>,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// local translation and rotation, relative to the parent if there is one
+    #[serde(default)]
+    pub translation: Vec2,
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default)]
+    pub rigid_body: Option<BodyKind>,
+    #[serde(default)]
+    pub collider: Option<ShapeSnapshot>,
+    #[serde(default)]
+    pub sensor: bool,
+    #[serde(default)]
+    pub linear_velocity: Vec2,
+    #[serde(default)]
+    pub angular_velocity: f32,
+    #[serde(default)]
+    pub gravity_scale: Option<f32>,
+    #[serde(default)]
+    pub locked_axes: Option<LockedAxesSnapshot>,
+    #[serde(default)]
+    pub restitution: Option<MaterialSnapshot>,
+    #[serde(default)]
+    pub friction: Option<MaterialSnapshot>,
+    #[serde(default)]
+    pub joint: Option<JointSnapshot>,
+    #[serde(default)]
+    pub debug_color: Option<[f32; 4]>,
+    /// `None` hides the axes, `DebugRender::default()` draws them at (0.5, 0.5)
+    #[serde(default)]
+    pub debug_axes: Option<Vec2>,
+    #[serde(default)]
+    pub lab: LabComponents,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BodyKind {
+    Dynamic,
+    Static,
+    Kinematic,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ShapeSnapshot {
+    Circle {
+        radius: f32,
+    },
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    RoundRectangle {
+        width: f32,
+        height: f32,
+        border_radius: f32,
+    },
+    Capsule {
+        a: Vec2,
+        b: Vec2,
+        radius: f32,
+    },
+    Triangle {
+        a: Vec2,
+        b: Vec2,
+        c: Vec2,
+    },
+    ConvexHull {
+        points: Vec<Vec2>,
+    },
+    /// Only written by hand in levels, captured colliders of these shapes become hulls
+    Ellipse {
+        half_width: f32,
+        half_height: f32,
+    },
+    RegularPolygon {
+        circumradius: f32,
+        sides: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct LockedAxesSnapshot {
+    pub translation_x: bool,
+    pub translation_y: bool,
+    pub rotation: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MaterialSnapshot {
+    /// the dynamic coefficient for friction
+    pub coefficient: f32,
+    /// friction only, snapshots without it use `coefficient` for both
+    #[serde(default)]
+    pub static_coefficient: Option<f32>,
+    pub combine_rule: CombineRule,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub enum CombineRule {
+    #[default]
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum JointSnapshot {
+    Prismatic {
+        entity1: usize,
+        entity2: usize,
+        local_anchor1: Vec2,
+        local_anchor2: Vec2,
+        free_axis: Vec2,
+        limits: Option<(f32, f32)>,
+        damping_linear: f32,
+        damping_angular: f32,
+        compliance: f32,
+    },
+    Revolute {
+        entity1: usize,
+        entity2: usize,
+        local_anchor1: Vec2,
+        local_anchor2: Vec2,
+        angle_limits: Option<(f32, f32)>,
+        damping_linear: f32,
+        damping_angular: f32,
+        compliance: f32,
+    },
+    Distance {
+        entity1: usize,
+        entity2: usize,
+        local_anchor1: Vec2,
+        local_anchor2: Vec2,
+        rest_length: f32,
+        limits: Option<(f32, f32)>,
+        damping_linear: f32,
+        damping_angular: f32,
+        compliance: f32,
+    },
+}
+
+/// Lab-specific components that drive demo behaviour
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LabComponents {
+    #[serde(default)]
+    pub magnet: Option<Magnet>,
+    #[serde(default)]
+    pub oscillating_magnet: Option<OscillatingMagnet>,
+    #[serde(default)]
+    pub conveyor_belt: Option<ConveyorBelt>,
+    #[serde(default)]
+    pub conveyor_belt_block_timer: Option<ConveyorBeltBlockTimer>,
+    #[serde(default)]
+    pub kinematic_platform: Option<KinematicPlatform>,
+    #[serde(default)]
+    pub destructible: Option<Destructible>,
+    #[serde(default)]
+    pub one_way_platform: bool,
+    #[serde(default)]
+    pub no_platform_carry: bool,
+}
+
+// events ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Event)]
+pub struct SaveSnapshot {
+    pub path: PathBuf,
+}
+
+/// Switches to the snapshot's demo first if it isn't the one running
+#[derive(Event)]
+pub struct LoadSnapshot {
+    pub path: PathBuf,
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// A loaded snapshot waiting for its demo to be entered
+#[derive(Resource)]
+pub struct PendingSnapshot(pub LabSnapshot);
+
+// conversions -----------------------------------------------------------------
+// -----------------------------------------------------------------------------
+impl BodyKind {
+    pub fn from_rigid_body(rigid_body: &RigidBody) -> Self {
+        match rigid_body {
+            RigidBody::Dynamic => Self::Dynamic,
+            RigidBody::Static => Self::Static,
+            RigidBody::Kinematic => Self::Kinematic,
+        }
+    }
+
+    pub fn to_rigid_body(self) -> RigidBody {
+        match self {
+            Self::Dynamic => RigidBody::Dynamic,
+            Self::Static => RigidBody::Static,
+            Self::Kinematic => RigidBody::Kinematic,
+        }
+    }
+}
+
+impl ShapeSnapshot {
+    pub fn from_collider(collider: &Collider) -> Option<Self> {
+        let shape = collider.shape();
+        if let Some(ball) = shape.as_ball() {
+            return Some(Self::Circle {
+                radius: ball.radius,
+            });
+        }
+        if let Some(cuboid) = shape.as_cuboid() {
+            return Some(Self::Rectangle {
+                width: cuboid.half_extents.x * 2.0,
+                height: cuboid.half_extents.y * 2.0,
+            });
+        }
+        if let Some(round_cuboid) = shape.as_round_cuboid() {
+            return Some(Self::RoundRectangle {
+                width: round_cuboid.inner_shape.half_extents.x * 2.0,
+                height: round_cuboid.inner_shape.half_extents.y * 2.0,
+                border_radius: round_cuboid.border_radius,
+            });
+        }
+        if let Some(capsule) = shape.as_capsule() {
+            return Some(Self::Capsule {
+                a: Vec2::new(capsule.segment.a.x, capsule.segment.a.y),
+                b: Vec2::new(capsule.segment.b.x, capsule.segment.b.y),
+                radius: capsule.radius,
+            });
+        }
+        if let Some(triangle) = shape.as_triangle() {
+            return Some(Self::Triangle {
+                a: Vec2::new(triangle.a.x, triangle.a.y),
+                b: Vec2::new(triangle.b.x, triangle.b.y),
+                c: Vec2::new(triangle.c.x, triangle.c.y),
+            });
+        }
+        if let Some(polygon) = shape.as_convex_polygon() {
+            return Some(Self::ConvexHull {
+                points: polygon
+                    .points()
+                    .iter()
+                    .map(|point| Vec2::new(point.x, point.y))
+                    .collect(),
+            });
+        }
+
+        // custom shapes only expose their support function, sample it into a hull
+        let support_map = shape.as_support_map()?;
+        let points = (0..HULL_SAMPLES)
+            .map(|i| {
+                let angle = TAU * i as f32 / HULL_SAMPLES as f32;
+                let point =
+                    support_map.local_support_point(&Vector2::new(angle.cos(), angle.sin()));
+                Vec2::new(point.x, point.y)
+            })
+            .collect();
+        Some(Self::ConvexHull { points })
+    }
+
+    /// The same shape scaled uniformly about its origin
+    pub fn scaled(&self, factor: f32) -> Self {
+        match self.clone() {
+            Self::Circle { radius } => Self::Circle {
+                radius: radius * factor,
+            },
+            Self::Rectangle { width, height } => Self::Rectangle {
+                width: width * factor,
+                height: height * factor,
+            },
+            Self::RoundRectangle {
+                width,
+                height,
+                border_radius,
+            } => Self::RoundRectangle {
+                width: width * factor,
+                height: height * factor,
+                border_radius: border_radius * factor,
+            },
+            Self::Capsule { a, b, radius } => Self::Capsule {
+                a: a * factor,
+                b: b * factor,
+                radius: radius * factor,
+            },
+            Self::Triangle { a, b, c } => Self::Triangle {
+                a: a * factor,
+                b: b * factor,
+                c: c * factor,
+            },
+            Self::ConvexHull { points } => Self::ConvexHull {
+                points: points.into_iter().map(|point| point * factor).collect(),
+            },
+            Self::Ellipse {
+                half_width,
+                half_height,
+            } => Self::Ellipse {
+                half_width: half_width * factor,
+                half_height: half_height * factor,
+            },
+            Self::RegularPolygon {
+                circumradius,
+                sides,
+            } => Self::RegularPolygon {
+                circumradius: circumradius * factor,
+                sides,
+            },
+        }
+    }
+
+    pub fn to_collider(&self) -> Option<Collider> {
+        Some(match self {
+            Self::Circle { radius } => Collider::circle(*radius),
+            Self::Rectangle { width, height } => Collider::rectangle(*width, *height),
+            Self::RoundRectangle {
+                width,
+                height,
+                border_radius,
+            } => Collider::round_rectangle(*width, *height, *border_radius),
+            Self::Capsule { a, b, radius } => Collider::capsule_endpoints(*a, *b, *radius),
+            Self::Triangle { a, b, c } => Collider::triangle(*a, *b, *c),
+            Self::ConvexHull { points } => Collider::convex_hull(points.clone())?,
+            Self::Ellipse {
+                half_width,
+                half_height,
+            } => Collider::ellipse(*half_width, *half_height),
+            Self::RegularPolygon {
+                circumradius,
+                sides,
+            } => Collider::regular_polygon(*circumradius, *sides),
+        })
+    }
+}
+
+impl LockedAxesSnapshot {
+    pub fn from_locked_axes(locked_axes: &LockedAxes) -> Self {
+        Self {
+            translation_x: locked_axes.is_translation_x_locked(),
+            translation_y: locked_axes.is_translation_y_locked(),
+            rotation: locked_axes.is_rotation_locked(),
+        }
+    }
+
+    pub fn to_locked_axes(self) -> LockedAxes {
+        let mut locked_axes = LockedAxes::new();
+        if self.translation_x {
+            locked_axes = locked_axes.lock_translation_x();
+        }
+        if self.translation_y {
+            locked_axes = locked_axes.lock_translation_y();
+        }
+        if self.rotation {
+            locked_axes = locked_axes.lock_rotation();
+        }
+        locked_axes
+    }
+}
+
+impl CombineRule {
+    pub fn from_coefficient_combine(combine_rule: CoefficientCombine) -> Self {
+        match combine_rule {
+            CoefficientCombine::Average => Self::Average,
+            CoefficientCombine::Min => Self::Min,
+            CoefficientCombine::Multiply => Self::Multiply,
+            CoefficientCombine::Max => Self::Max,
+        }
+    }
+
+    pub fn to_coefficient_combine(self) -> CoefficientCombine {
+        match self {
+            Self::Average => CoefficientCombine::Average,
+            Self::Min => CoefficientCombine::Min,
+            Self::Multiply => CoefficientCombine::Multiply,
+            Self::Max => CoefficientCombine::Max,
+        }
+    }
+}
+
+impl MaterialSnapshot {
+    pub fn from_restitution(restitution: &Restitution) -> Self {
+        Self {
+            coefficient: restitution.coefficient,
+            static_coefficient: None,
+            combine_rule: CombineRule::from_coefficient_combine(restitution.combine_rule),
+        }
+    }
+
+    pub fn to_restitution(self) -> Restitution {
+        Restitution::new(self.coefficient)
+            .with_combine_rule(self.combine_rule.to_coefficient_combine())
+    }
+
+    pub fn from_friction(friction: &Friction) -> Self {
+        Self {
+            coefficient: friction.dynamic_coefficient,
+            static_coefficient: Some(friction.static_coefficient),
+            combine_rule: CombineRule::from_coefficient_combine(friction.combine_rule),
+        }
+    }
+
+    pub fn to_friction(self) -> Friction {
+        Friction::new(self.coefficient)
+            .with_static_coefficient(self.static_coefficient.unwrap_or(self.coefficient))
+            .with_combine_rule(self.combine_rule.to_coefficient_combine())
+    }
+}
+
+impl JointSnapshot {
+    pub fn from_prismatic(joint: &PrismaticJoint, entity1: usize, entity2: usize) -> Self {
+        Self::Prismatic {
+            entity1,
+            entity2,
+            local_anchor1: joint.local_anchor1,
+            local_anchor2: joint.local_anchor2,
+            free_axis: joint.free_axis,
+            limits: joint.free_axis_limits.map(|limit| (limit.min, limit.max)),
+            damping_linear: joint.damping_linear,
+            damping_angular: joint.damping_angular,
+            compliance: joint.compliance,
+        }
+    }
+
+    pub fn from_revolute(joint: &RevoluteJoint, entity1: usize, entity2: usize) -> Self {
+        Self::Revolute {
+            entity1,
+            entity2,
+            local_anchor1: joint.local_anchor1,
+            local_anchor2: joint.local_anchor2,
+            angle_limits: joint.angle_limit.map(|limit| (limit.alpha, limit.beta)),
+            damping_linear: joint.damping_linear,
+            damping_angular: joint.damping_angular,
+            compliance: joint.compliance,
+        }
+    }
+
+    pub fn from_distance(joint: &DistanceJoint, entity1: usize, entity2: usize) -> Self {
+        Self::Distance {
+            entity1,
+            entity2,
+            local_anchor1: joint.local_anchor1,
+            local_anchor2: joint.local_anchor2,
+            rest_length: joint.rest_length,
+            limits: joint.length_limits.map(|limit| (limit.min, limit.max)),
+            damping_linear: joint.damping_linear,
+            damping_angular: joint.damping_angular,
+            compliance: joint.compliance,
+        }
+    }
+
+    /// Inserts the joint component, `entities` maps snapshot indices to spawned entities
+    pub fn insert(&self, entity: &mut EntityWorldMut, entities: &[Entity]) {
+        match *self {
+            Self::Prismatic {
+                entity1,
+                entity2,
+                local_anchor1,
+                local_anchor2,
+                free_axis,
+                limits,
+                damping_linear,
+                damping_angular,
+                compliance,
+            } => {
+                let mut joint = PrismaticJoint::new(entities[entity1], entities[entity2])
+                    .with_local_anchor_1(local_anchor1)
+                    .with_local_anchor_2(local_anchor2)
+                    .with_free_axis(free_axis)
+                    .with_linear_velocity_damping(damping_linear)
+                    .with_angular_velocity_damping(damping_angular)
+                    .with_compliance(compliance);
+                if let Some((min, max)) = limits {
+                    joint = joint.with_limits(min, max);
+                }
+                entity.insert(joint);
+            }
+            Self::Revolute {
+                entity1,
+                entity2,
+                local_anchor1,
+                local_anchor2,
+                angle_limits,
+                damping_linear,
+                damping_angular,
+                compliance,
+            } => {
+                let mut joint = RevoluteJoint::new(entities[entity1], entities[entity2])
+                    .with_local_anchor_1(local_anchor1)
+                    .with_local_anchor_2(local_anchor2)
+                    .with_linear_velocity_damping(damping_linear)
+                    .with_angular_velocity_damping(damping_angular)
+                    .with_compliance(compliance);
+                if let Some((alpha, beta)) = angle_limits {
+                    joint = joint.with_angle_limits(alpha, beta);
+                }
+                entity.insert(joint);
+            }
+            Self::Distance {
+                entity1,
+                entity2,
+                local_anchor1,
+                local_anchor2,
+                rest_length,
+                limits,
+                damping_linear,
+                damping_angular,
+                compliance,
+            } => {
+                let mut joint = DistanceJoint::new(entities[entity1], entities[entity2])
+                    .with_local_anchor_1(local_anchor1)
+                    .with_local_anchor_2(local_anchor2)
+                    .with_rest_length(rest_length)
+                    .with_linear_velocity_damping(damping_linear)
+                    .with_angular_velocity_damping(damping_angular)
+                    .with_compliance(compliance);
+                if let Some((min, max)) = limits {
+                    joint = joint.with_limits(min, max);
+                }
+                entity.insert(joint);
+            }
+        }
+    }
+}
+
+fn transform_angle(transform: &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::XYZ).2
+}
+
+// capture ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Root entities of the running demo that take part in the simulation
+pub fn physics_roots(world: &mut World) -> Vec<Entity> {
+    world
+        .query_filtered::<Entity, (
+            With<DemoScoped>,
+            Without<Player>,
+            Without<Parent>,
+            Or<(With<RigidBody>, With<Collider>)>,
+        )>()
+        .iter(world)
+        .collect()
+}
+
+/// Snapshots `roots` and all of their descendants
+pub fn capture_entities(world: &mut World, roots: &[Entity]) -> Vec<EntitySnapshot> {
+    // first pass: number every entity so parents and joints can refer to each other
+    let mut ordered: Vec<(Entity, Option<usize>)> =
+        roots.iter().map(|&root| (root, None)).collect();
+    let mut i = 0;
+    while i < ordered.len() {
+        if let Some(children) = world.get::<Children>(ordered[i].0) {
+            ordered.extend(children.iter().map(|&child| (child, Some(i))));
+        }
+        i += 1;
+    }
+    let indices: HashMap<Entity, usize> = ordered
+        .iter()
+        .enumerate()
+        .map(|(index, (entity, _))| (*entity, index))
+        .collect();
+    let index_of = |entity: Entity| indices.get(&entity).copied();
+
+    // second pass: record components
+    let mut snapshots = Vec::with_capacity(ordered.len());
+    for &(entity, parent) in ordered.iter() {
+        let entity_ref = world.entity(entity);
+        let transform = entity_ref.get::<Transform>().copied().unwrap_or_default();
+        let debug_render = entity_ref.get::<DebugRender>();
+
+        let joint = if let Some(joint) = entity_ref.get::<PrismaticJoint>() {
+            index_of(joint.entity1)
+                .zip(index_of(joint.entity2))
+                .map(|(e1, e2)| JointSnapshot::from_prismatic(joint, e1, e2))
+        } else if let Some(joint) = entity_ref.get::<RevoluteJoint>() {
+            index_of(joint.entity1)
+                .zip(index_of(joint.entity2))
+                .map(|(e1, e2)| JointSnapshot::from_revolute(joint, e1, e2))
+        } else if let Some(joint) = entity_ref.get::<DistanceJoint>() {
+            index_of(joint.entity1)
+                .zip(index_of(joint.entity2))
+                .map(|(e1, e2)| JointSnapshot::from_distance(joint, e1, e2))
+        } else {
+            None
+        };
+
+        snapshots.push(EntitySnapshot {
+            parent,
+            name: entity_ref.get::<Name>().map(|name| name.to_string()),
+            translation: transform.translation.xy(),
+            rotation: transform_angle(&transform),
+            rigid_body: entity_ref.get::<RigidBody>().map(BodyKind::from_rigid_body),
+            collider: entity_ref
+                .get::<Collider>()
+                .and_then(ShapeSnapshot::from_collider),
+            sensor: entity_ref.contains::<Sensor>(),
+            linear_velocity: entity_ref
+                .get::<LinearVelocity>()
+                .map_or(Vec2::ZERO, |velocity| velocity.0),
+            angular_velocity: entity_ref
+                .get::<AngularVelocity>()
+                .map_or(0.0, |velocity| velocity.0),
+            gravity_scale: entity_ref.get::<GravityScale>().map(|scale| scale.0),
+            locked_axes: entity_ref
+                .get::<LockedAxes>()
+                .map(LockedAxesSnapshot::from_locked_axes),
+            restitution: entity_ref
+                .get::<Restitution>()
+                .map(MaterialSnapshot::from_restitution),
+            friction: entity_ref
+                .get::<Friction>()
+                .map(MaterialSnapshot::from_friction),
+            joint,
+            debug_color: debug_render
+                .and_then(|render| render.collider_color)
+                .map(|color| color.as_rgba_f32()),
+            debug_axes: debug_render.and_then(|render| render.axis_lengths),
+            lab: LabComponents {
+                magnet: entity_ref.get::<Magnet>().cloned(),
+                oscillating_magnet: entity_ref.get::<OscillatingMagnet>().cloned(),
+                conveyor_belt: entity_ref.get::<ConveyorBelt>().cloned(),
+                conveyor_belt_block_timer: entity_ref.get::<ConveyorBeltBlockTimer>().cloned(),
+                kinematic_platform: entity_ref.get::<KinematicPlatform>().cloned(),
+                destructible: entity_ref.get::<Destructible>().cloned(),
+                one_way_platform: entity_ref.contains::<OneWayPlatform>(),
+                no_platform_carry: entity_ref.contains::<NoPlatformCarry>(),
+            },
+        });
+    }
+
+    snapshots
+}
+
+pub fn capture_snapshot(world: &mut World) -> LabSnapshot {
+    let roots = physics_roots(world);
+    let entities = capture_entities(world, &roots);
+
+    let player = world
+        .query_filtered::<(&Transform, &LinearVelocity, &AngularVelocity), With<Player>>()
+        .get_single(world)
+        .ok()
+        .map(|(transform, linear_velocity, angular_velocity)| BodyState {
+            translation: transform.translation.xy(),
+            rotation: transform_angle(transform),
+            linear_velocity: linear_velocity.0,
+            angular_velocity: angular_velocity.0,
+        });
+
+    LabSnapshot {
+        demo: *world.resource::<State<DemoState>>().get(),
+        gravity: world.resource::<Gravity>().0,
+        player,
+        entities,
+    }
+}
+
+// restore ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Spawns `snapshots`, tagging the roots with `scope`. Returns the spawned entities in snapshot order.
+pub fn spawn_entities(
+    world: &mut World,
+    snapshots: &[EntitySnapshot],
+    scope: DemoScoped,
+) -> Vec<Entity> {
+    // reserve every entity up front so joints can refer to bodies later in the list
+    let entities: Vec<Entity> = snapshots.iter().map(|_| world.spawn_empty().id()).collect();
+
+    for (snapshot, &entity) in snapshots.iter().zip(entities.iter()) {
+        if let Some(parent) = snapshot.parent {
+            world.entity_mut(entities[parent]).add_child(entity);
+        }
+
+        let mut entity_mut = world.entity_mut(entity);
+        if snapshot.parent.is_none() {
+            entity_mut.insert(scope);
+        }
+        if let Some(name) = &snapshot.name {
+            entity_mut.insert(Name::new(name.clone()));
+        }
+        if snapshot.rigid_body.is_some() || snapshot.collider.is_some() {
+            entity_mut.insert(TransformBundle::from_transform(
+                Transform::from_translation(snapshot.translation.extend(0.0))
+                    .with_rotation(Quat::from_rotation_z(snapshot.rotation)),
+            ));
+        }
+        if let Some(rigid_body) = snapshot.rigid_body {
+            entity_mut.insert((
+                rigid_body.to_rigid_body(),
+                LinearVelocity(snapshot.linear_velocity),
+                AngularVelocity(snapshot.angular_velocity),
+            ));
+        }
+        if let Some(collider) = snapshot
+            .collider
+            .as_ref()
+            .and_then(ShapeSnapshot::to_collider)
+        {
+            entity_mut.insert(collider);
+        }
+        if snapshot.sensor {
+            entity_mut.insert(Sensor);
+        }
+        if let Some(gravity_scale) = snapshot.gravity_scale {
+            entity_mut.insert(GravityScale(gravity_scale));
+        }
+        if let Some(locked_axes) = snapshot.locked_axes {
+            entity_mut.insert(locked_axes.to_locked_axes());
+        }
+        if let Some(restitution) = snapshot.restitution {
+            entity_mut.insert(restitution.to_restitution());
+        }
+        if let Some(friction) = snapshot.friction {
+            entity_mut.insert(friction.to_friction());
+        }
+        if let Some(joint) = &snapshot.joint {
+            joint.insert(&mut entity_mut, &entities);
+        }
+        if snapshot.debug_color.is_some() || snapshot.debug_axes.is_some() {
+            entity_mut.insert(DebugRender {
+                collider_color: snapshot.debug_color.map(Color::rgba_from_array),
+                axis_lengths: snapshot.debug_axes,
+                ..default()
+            });
+        }
+
+        let lab = snapshot.lab.clone();
+        if let Some(magnet) = lab.magnet {
+            entity_mut.insert(magnet);
+        }
+        if let Some(oscillating_magnet) = lab.oscillating_magnet {
+            entity_mut.insert(oscillating_magnet);
+        }
+        if let Some(conveyor_belt) = lab.conveyor_belt {
+            entity_mut.insert(conveyor_belt);
+        }
+        if let Some(block_timer) = lab.conveyor_belt_block_timer {
+            entity_mut.insert(block_timer);
+        }
+        if let Some(kinematic_platform) = lab.kinematic_platform {
+            entity_mut.insert(kinematic_platform);
+        }
+        if let Some(destructible) = lab.destructible {
+            entity_mut.insert(destructible);
+        }
+        if lab.one_way_platform {
+            entity_mut.insert(OneWayPlatform::default());
+        }
+        if lab.no_platform_carry {
+            entity_mut.insert(NoPlatformCarry);
+        }
+    }
+
+    entities
+}
+
+/// Replaces the running demo's physics entities with the snapshot's.
+/// Bookkeeping entities (timers) are kept, the player is moved rather than respawned.
+pub fn restore_snapshot(world: &mut World, snapshot: &LabSnapshot) {
+    for root in physics_roots(world) {
+        world.entity_mut(root).despawn_recursive();
+    }
+
+    spawn_entities(world, &snapshot.entities, DemoScoped(snapshot.demo));
+    world.resource_mut::<Gravity>().0 = snapshot.gravity;
+
+    if let Some(player_state) = snapshot.player {
+        let mut player_query = world.query_filtered::<(
+            &mut Transform,
+            &mut Position,
+            &mut Rotation,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+        ), With<Player>>();
+        if let Ok((
+            mut transform,
+            mut position,
+            mut rotation,
+            mut linear_velocity,
+            mut angular_velocity,
+        )) = player_query.get_single_mut(world)
+        {
+            transform.translation = player_state.translation.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(player_state.rotation);
+            position.0 = player_state.translation;
+            *rotation = Rotation::from_radians(player_state.rotation);
+            linear_velocity.0 = player_state.linear_velocity;
+            angular_velocity.0 = player_state.angular_velocity;
+        }
+    }
+}
+
+pub fn write_snapshot(snapshot: &LabSnapshot, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let ron = ron::ser::to_string_pretty(snapshot, ron::ser::PrettyConfig::default())?;
+    fs::write(path, ron)?;
+    Ok(())
+}
+
+pub fn read_snapshot(path: &Path) -> Result<LabSnapshot, Box<dyn Error>> {
+    Ok(ron::from_str(&fs::read_to_string(path)?)?)
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn handle_snapshot_input(
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut write_save: EventWriter<SaveSnapshot>,
+    mut write_load: EventWriter<LoadSnapshot>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+
+    if action_state.just_pressed(&PlayerAction::SaveSnapshot) {
+        write_save.send(SaveSnapshot {
+            path: DEFAULT_SNAPSHOT_PATH.into(),
+        });
+    }
+    if action_state.just_pressed(&PlayerAction::LoadSnapshot) {
+        write_load.send(LoadSnapshot {
+            path: DEFAULT_SNAPSHOT_PATH.into(),
+        });
+    }
+}
+
+pub fn handle_save_snapshot(world: &mut World) {
+    let requests: Vec<SaveSnapshot> = world
+        .resource_mut::<Events<SaveSnapshot>>()
+        .drain()
+        .collect();
+
+    for request in requests {
+        let snapshot = capture_snapshot(world);
+        match write_snapshot(&snapshot, &request.path) {
+            Ok(()) => info!(
+                "saved {} entities to {}",
+                snapshot.entities.len(),
+                request.path.display()
+            ),
+            Err(err) => error!(
+                "failed to save snapshot to {}: {err}",
+                request.path.display()
+            ),
+        }
+    }
+}
+
+pub fn handle_load_snapshot(
+    mut commands: Commands,
+    current_demo_state: Res<State<DemoState>>,
+    mut next_demo_state: ResMut<NextState<DemoState>>,
+    mut read_load: EventReader<LoadSnapshot>,
+) {
+    for request in read_load.read() {
+        match read_snapshot(&request.path) {
+            Ok(snapshot) => {
+                if snapshot.demo != *current_demo_state.get() {
+                    next_demo_state.set(snapshot.demo);
+                }
+                commands.insert_resource(PendingSnapshot(snapshot));
+            }
+            Err(err) => error!(
+                "failed to load snapshot from {}: {err}",
+                request.path.display()
+            ),
+        }
+    }
+}
+
+/// Restores a loaded snapshot once its demo is running (the frame after the transition)
+pub fn apply_pending_snapshot(world: &mut World) {
+    let Some(pending) = world.get_resource::<PendingSnapshot>() else {
+        return;
+    };
+    if pending.0.demo != *world.resource::<State<DemoState>>().get() {
+        return;
+    }
+
+    let PendingSnapshot(snapshot) = world
+        .remove_resource::<PendingSnapshot>()
+        .expect("PendingSnapshot was checked above");
+    restore_snapshot(world, &snapshot);
+    info!("restored {} entities", snapshot.entities.len());
+}
@@ -0,0 +1,172 @@
+use super::{
+    demo_state::DemoWorldSnapshot,
+    player::{Player, PlayerAction},
+};
+
+use bevy::{prelude::*, utils::Duration};
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+// constants -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Relative physics speeds cycled through by `PlayerAction::CycleTimeScale`
+pub const TIME_SCALES: [f32; 6] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0];
+const DEFAULT_TIME_SCALE_INDEX: usize = 3;
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct TimeControlsPlugin;
+
+impl Plugin for TimeControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // resources -------------------------------------------------------
+            .init_resource::<TimeControls>()
+            // Update ----------------------------------------------------------
+            .add_systems(Update, handle_time_control_input)
+            // PostUpdate ------------------------------------------------------
+            .add_systems(
+                PostUpdate,
+                (
+                    begin_single_step.before(PhysicsSet::Prepare),
+                    end_single_step.after(PhysicsSet::Sync),
+                ),
+            );
+    }
+}
+
+// resources -------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Resource)]
+pub struct TimeControls {
+    pub scale_index: usize,
+    /// physics ticks requested while paused, run one per frame
+    pub pending_steps: u32,
+    /// timestep mode to restore once the current single step has run
+    stepping_from: Option<TimestepMode>,
+}
+
+impl Default for TimeControls {
+    fn default() -> Self {
+        Self {
+            scale_index: DEFAULT_TIME_SCALE_INDEX,
+            pending_steps: 0,
+            stepping_from: None,
+        }
+    }
+}
+
+impl TimeControls {
+    pub fn time_scale(&self) -> f32 {
+        TIME_SCALES[self.scale_index]
+    }
+}
+
+// components ------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Component)]
+pub struct TimeIndicator;
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn handle_time_control_input(
+    action_query: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut controls: ResMut<TimeControls>,
+    mut physics_time: ResMut<Time<Physics>>,
+    world_snapshot: Option<ResMut<DemoWorldSnapshot>>,
+) {
+    let Ok(action_state) = action_query.get_single() else {
+        return;
+    };
+
+    if action_state.just_pressed(&PlayerAction::TogglePause) {
+        if physics_time.is_paused() {
+            physics_time.unpause();
+        } else {
+            physics_time.pause();
+        }
+    }
+
+    if action_state.just_pressed(&PlayerAction::StepPhysics) && physics_time.is_paused() {
+        controls.pending_steps += 1;
+    }
+
+    if action_state.just_pressed(&PlayerAction::CycleTimeScale) {
+        controls.scale_index = (controls.scale_index + 1) % TIME_SCALES.len();
+        let time_scale = controls.time_scale();
+        physics_time.set_relative_speed(time_scale);
+
+        if let Some(mut world_snapshot) = world_snapshot {
+            world_snapshot.physics_speed = time_scale;
+        }
+    }
+}
+
+/// Runs exactly one physics tick this frame by briefly unpausing with a single fixed step
+pub fn begin_single_step(
+    mut controls: ResMut<TimeControls>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if controls.pending_steps == 0 || !physics_time.is_paused() {
+        return;
+    }
+    controls.pending_steps -= 1;
+
+    let delta = match *physics_time.timestep_mode() {
+        TimestepMode::Fixed { delta, .. } | TimestepMode::FixedOnce { delta } => delta,
+        TimestepMode::Variable { .. } => Duration::from_secs_f64(1.0 / 60.0),
+    };
+    controls.stepping_from = Some(std::mem::replace(
+        physics_time.timestep_mode_mut(),
+        TimestepMode::FixedOnce { delta },
+    ));
+    physics_time.unpause();
+}
+
+pub fn end_single_step(
+    mut controls: ResMut<TimeControls>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if let Some(timestep_mode) = controls.stepping_from.take() {
+        *physics_time.timestep_mode_mut() = timestep_mode;
+        physics_time.pause();
+    }
+}
+
+pub fn spawn_time_indicator(mut commands: Commands) {
+    commands.spawn((
+        Name::new("TimeIndicator"),
+        TimeIndicator,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_time_indicator(
+    controls: Res<TimeControls>,
+    physics_time: Res<Time<Physics>>,
+    mut indicator_query: Query<&mut Text, With<TimeIndicator>>,
+) {
+    let Ok(mut text) = indicator_query.get_single_mut() else {
+        return;
+    };
+
+    let time_scale = controls.time_scale();
+    text.sections[0].value = match (physics_time.is_paused(), time_scale == 1.0) {
+        (true, _) => format!("PAUSED  x{time_scale:.2}"),
+        (false, false) => format!("x{time_scale:.2}"),
+        (false, true) => String::new(),
+    };
+}
@@ -0,0 +1,206 @@
+use super::demo_state::{enter_demo_now, DemoState};
+
+use bevy::{
+    prelude::*,
+    reflect::{
+        serde::{TypedReflectDeserializer, TypedReflectSerializer},
+        GetTypeRegistration, TypePath,
+    },
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContext, EguiPlugin},
+    bevy_inspector, egui, DefaultInspectorConfigPlugin,
+};
+use serde::de::DeserializeSeed;
+
+use std::{collections::BTreeMap, error::Error};
+
+// plugins ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// The tuning window, parameter sets themselves are registered with `TuningAppExt`
+pub struct TuningPanelPlugin;
+
+impl Plugin for TuningPanelPlugin {
+    fn build(&self, app: &mut App) {
+        // the world inspector may have added these already
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        if !app.is_plugin_added::<DefaultInspectorConfigPlugin>() {
+            app.add_plugins(DefaultInspectorConfigPlugin);
+        }
+
+        app
+            // Update ----------------------------------------------------------
+            .add_systems(Update, tuning_panel_ui);
+    }
+}
+
+// registry --------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub struct TuningEntry {
+    /// `None` for lab-wide parameters shown in every demo
+    pub demo: Option<DemoState>,
+    pub name: &'static str,
+    ui: fn(&mut World, &mut egui::Ui),
+    reset: fn(&mut World),
+    save: fn(&World) -> Result<String, Box<dyn Error>>,
+    load: fn(&mut World, &str) -> Result<(), Box<dyn Error>>,
+}
+
+#[derive(Resource, Default)]
+pub struct TuningRegistry {
+    entries: Vec<TuningEntry>,
+}
+
+impl TuningRegistry {
+    pub fn iter(&self) -> impl Iterator<Item = &TuningEntry> {
+        self.entries.iter()
+    }
+}
+
+pub trait TuningAppExt {
+    /// Adds `P` as a resource at its default value and lists it in the tuning panel.
+    /// Systems read `P` either at spawn (applied on demo reset) or every frame (applied live).
+    fn add_tuning<P>(&mut self, demo: Option<DemoState>) -> &mut Self
+    where
+        P: Resource + Reflect + TypePath + GetTypeRegistration + Default;
+}
+
+impl TuningAppExt for App {
+    fn add_tuning<P>(&mut self, demo: Option<DemoState>) -> &mut Self
+    where
+        P: Resource + Reflect + TypePath + GetTypeRegistration + Default,
+    {
+        self.init_resource::<TuningRegistry>()
+            .init_resource::<P>()
+            .register_type::<P>();
+
+        let name = P::short_type_path();
+        self.world
+            .resource_mut::<TuningRegistry>()
+            .entries
+            .push(TuningEntry {
+                demo,
+                name,
+                ui: |world, ui| {
+                    bevy_inspector::ui_for_resource::<P>(world, ui);
+                },
+                reset: |world| world.insert_resource(P::default()),
+                save: |world| {
+                    let registry = world.resource::<AppTypeRegistry>().read();
+                    let params = world.resource::<P>();
+                    Ok(ron::to_string(&TypedReflectSerializer::new(
+                        params, &registry,
+                    ))?)
+                },
+                load: |world, ron_params| {
+                    let registry = world.resource::<AppTypeRegistry>().clone();
+                    let registry = registry.read();
+                    let registration = registry
+                        .get(std::any::TypeId::of::<P>())
+                        .ok_or("tuning type is not registered")?;
+                    let mut deserializer = ron::Deserializer::from_str(ron_params)?;
+                    let value = TypedReflectDeserializer::new(registration, &registry)
+                        .deserialize(&mut deserializer)?;
+
+                    let mut params = P::default();
+                    params.apply(&*value);
+                    world.insert_resource(params);
+                    Ok(())
+                },
+            });
+
+        self
+    }
+}
+
+// saving ----------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+/// Every tuning parameter set as ron, by name
+pub fn save_tuning(world: &World) -> BTreeMap<String, String> {
+    let Some(registry) = world.get_resource::<TuningRegistry>() else {
+        return BTreeMap::new();
+    };
+    registry
+        .iter()
+        .filter_map(|entry| match (entry.save)(world) {
+            Ok(ron_params) => Some((entry.name.to_owned(), ron_params)),
+            Err(err) => {
+                error!("failed to save {} tuning: {err}", entry.name);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Replaces the tuning parameters with saved ones, sets missing from `saved` are left alone
+pub fn load_tuning(world: &mut World, saved: &BTreeMap<String, String>) {
+    let loaders: HashMap<&'static str, _> = world
+        .get_resource::<TuningRegistry>()
+        .into_iter()
+        .flat_map(TuningRegistry::iter)
+        .map(|entry| (entry.name, entry.load))
+        .collect();
+
+    for (name, ron_params) in saved {
+        let Some(load) = loaders.get(name.as_str()) else {
+            warn!("no tuning named {name}, skipped");
+            continue;
+        };
+        if let Err(err) = load(world, ron_params) {
+            error!("failed to load {name} tuning: {err}");
+        }
+    }
+}
+
+// systems ---------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+pub fn tuning_panel_ui(world: &mut World) {
+    let Ok(egui_context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+
+    let demo = *world.resource::<State<DemoState>>().get();
+    let entries: Vec<_> = world
+        .get_resource::<TuningRegistry>()
+        .into_iter()
+        .flat_map(TuningRegistry::iter)
+        .filter(|entry| entry.demo.is_none() || entry.demo == Some(demo))
+        .map(|entry| (entry.name, entry.ui, entry.reset))
+        .collect();
+
+    let mut reset_demo = false;
+    egui::Window::new("Tuning")
+        .default_open(false)
+        .show(egui_context.get_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (name, params_ui, reset) in entries {
+                    egui::CollapsingHeader::new(name)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            params_ui(world, ui);
+                            if ui.button("Defaults").clicked() {
+                                reset(world);
+                            }
+                        });
+                }
+
+                ui.separator();
+                reset_demo = ui
+                    .button("Reset demo")
+                    .on_hover_text("respawn the demo to apply spawn-time parameters")
+                    .clicked();
+            });
+        });
+
+    if reset_demo {
+        enter_demo_now(world, demo);
+    }
+}
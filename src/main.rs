@@ -1,26 +1,119 @@
-mod game;
+use bevy_physics_lab::game::{demo_state::DemoState, game_plugin, headless::HeadlessLab};
 
-use game::game_plugin;
-
-use bevy::{log::LogPlugin, prelude::*, window::WindowMode};
+use bevy::{
+    log::LogPlugin,
+    prelude::*,
+    utils::Instant,
+    window::{WindowMode, WindowResolution},
+};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_screen_diagnostics::{ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin};
+use bevy_xpbd_2d::prelude::*;
+use clap::Parser;
+
+// CLI -------------------------------------------------------------------------
+// -----------------------------------------------------------------------------
+#[derive(Parser)]
+#[command(about = "Experimenting with external physics libraries in Bevy")]
+struct Cli {
+    /// Demo to start in
+    #[arg(long, value_enum, default_value_t = DemoState::Home)]
+    demo: DemoState,
+
+    /// Borderless fullscreen on the primary monitor
+    #[arg(long, conflicts_with = "headless")]
+    fullscreen: bool,
+
+    /// Window size as WIDTHxHEIGHT, e.g. 1920x1080
+    #[arg(long, value_parser = parse_resolution, conflicts_with = "headless")]
+    resolution: Option<(f32, f32)>,
+
+    /// Physics substeps per step
+    #[arg(long)]
+    substeps: Option<u32>,
+
+    /// Physics step in seconds, e.g. 0.008 for 125 Hz
+    #[arg(long, value_parser = parse_timestep)]
+    timestep: Option<f64>,
+
+    /// Start without the world inspector
+    #[arg(long)]
+    no_inspector: bool,
+
+    /// Start without the on-screen frame diagnostics
+    #[arg(long)]
+    no_diagnostics: bool,
+
+    /// Run the demo without a window for `--ticks` physics steps, then print a summary
+    #[arg(long)]
+    headless: bool,
+
+    #[arg(long, default_value_t = 600, requires = "headless")]
+    ticks: u32,
+}
+
+fn parse_resolution(arg: &str) -> Result<(f32, f32), String> {
+    let (width, height) = arg
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {arg}"))?;
+    let parse = |value: &str| {
+        let size = value
+            .trim()
+            .parse::<f32>()
+            .map_err(|err| format!("invalid size {value}: {err}"))?;
+        if size.is_finite() && size > 0.0 {
+            Ok(size)
+        } else {
+            Err(format!("size must be positive, got {value}"))
+        }
+    };
+    Ok((parse(width)?, parse(height)?))
+}
 
-use std::error::Error;
+fn parse_timestep(arg: &str) -> Result<f64, String> {
+    let timestep = arg
+        .trim()
+        .parse::<f64>()
+        .map_err(|err| format!("invalid timestep {arg}: {err}"))?;
+    if timestep.is_finite() && timestep > 0.0 {
+        Ok(timestep)
+    } else {
+        Err(format!(
+            "timestep must be a positive number of seconds, got {arg}"
+        ))
+    }
+}
 
 // MAIN ------------------------------------------------------------------------
 // -----------------------------------------------------------------------------
-fn main() -> Result<(), Box<dyn Error>> {
-    App::new()
+fn main() {
+    let cli = Cli::parse();
+
+    if cli.headless {
+        run_headless(&cli);
+        return;
+    }
+
+    let mut window = Window {
+        mode: if cli.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        },
+        position: WindowPosition::Centered(MonitorSelection::Primary),
+        ..default()
+    };
+    if let Some((width, height)) = cli.resolution {
+        window.resolution = WindowResolution::new(width, height);
+    }
+
+    let mut app = App::new();
+    app
         // bevy plugins
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        mode: WindowMode::Windowed,
-                        position: WindowPosition::Centered(MonitorSelection::Primary),
-                        ..default()
-                    }),
+                    primary_window: Some(window),
                     ..default()
                 })
                 .set(ImagePlugin::default_nearest())
@@ -31,17 +124,67 @@ fn main() -> Result<(), Box<dyn Error>> {
                     level: bevy::log::Level::INFO,
                     ..default()
                 }),
-        )
-        // non-bevy external plugins
-        .add_plugins((
+        );
+
+    // non-bevy external plugins
+    if !cli.no_diagnostics {
+        app.add_plugins((
             ScreenDiagnosticsPlugin::default(),
             ScreenFrameDiagnosticsPlugin,
-            WorldInspectorPlugin::new(),
-        ))
-        // local plugins
-        .add_plugins((game_plugin::GamePlugin,))
-        .insert_resource(ClearColor(Color::BLACK))
-        .run();
+        ));
+    }
+    if !cli.no_inspector {
+        app.add_plugins(WorldInspectorPlugin::new());
+    }
+
+    // local plugins
+    app.add_plugins((game_plugin::GamePlugin {
+        starting_demo: cli.demo,
+    },))
+        .insert_resource(ClearColor(Color::BLACK));
+
+    if let Some(substeps) = cli.substeps {
+        app.insert_resource(SubstepCount(substeps));
+    }
+    if let Some(timestep) = cli.timestep {
+        app.insert_resource(Time::new_with(Physics::fixed_hz(1.0 / timestep)));
+    }
+
+    app.run();
+}
+
+/// Steps the demo as fast as possible, one physics step per tick
+fn run_headless(cli: &Cli) {
+    let mut lab = match cli.timestep {
+        Some(timestep) => HeadlessLab::with_tick_hz(cli.demo, 1.0 / timestep),
+        None => HeadlessLab::new(cli.demo),
+    };
+    if let Some(substeps) = cli.substeps {
+        lab.world().insert_resource(SubstepCount(substeps));
+    }
+
+    let started = Instant::now();
+    lab.run_ticks(cli.ticks);
+    let wall_secs = started.elapsed().as_secs_f64();
+
+    let tick_hz = lab.tick_hz;
+    let world = lab.world();
+    let substeps = world.resource::<SubstepCount>().0;
+    let bodies = world.query::<&RigidBody>().iter(world).count();
+    let sleeping = world
+        .query_filtered::<(), (With<RigidBody>, With<Sleeping>)>()
+        .iter(world)
+        .count();
 
-    Ok(())
+    println!("demo:       {:?}", cli.demo);
+    println!(
+        "ticks:      {} at {tick_hz:.1} Hz, {substeps} substeps",
+        cli.ticks
+    );
+    println!(
+        "simulated:  {:.2} s in {wall_secs:.2} s wall time ({:.0} ticks/s)",
+        f64::from(cli.ticks) / tick_hz,
+        f64::from(cli.ticks) / wall_secs.max(f64::EPSILON)
+    );
+    println!("bodies:     {bodies} ({sleeping} sleeping)");
 }
@@ -0,0 +1,280 @@
+use bevy_physics_lab::game::{
+    demo_state::DemoState,
+    game_plugin::GravityFactor,
+    headless::HeadlessLab,
+    physics_demos::{
+        conveyor_belt_demo::ConveyorBeltBlockTimer, destructible_demo::Destructible,
+        magnet_demo::Magnet,
+    },
+    replay::{read_recording, ReplayDiverged, StartRecording, StartReplay, StopRecording},
+    snapshot::{capture_snapshot, read_snapshot, restore_snapshot, write_snapshot, LabSnapshot},
+};
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+#[test]
+fn conveyor_blocks_reach_bottom_belt() {
+    let mut lab = HeadlessLab::new(DemoState::ConveyorBelt);
+    lab.run_ticks(900);
+
+    // blocks enter at the top left, ride the top and middle belts, then land on the bottom belt
+    let world = lab.world();
+    let on_bottom_belt = world
+        .query_filtered::<&Transform, With<ConveyorBeltBlockTimer>>()
+        .iter(world)
+        .any(|transform| {
+            let pos = transform.translation.xy();
+            (-100.0..0.0).contains(&pos.x) && (-20.0..0.0).contains(&pos.y)
+        });
+
+    assert!(on_bottom_belt, "no conveyor block reached the bottom belt");
+}
+
+#[test]
+fn colliders_level_spawns() {
+    let mut lab = HeadlessLab::new(DemoState::Colliders);
+    lab.run_ticks(1);
+
+    let world = lab.world();
+    let mut names = world
+        .query::<&Name>()
+        .iter(world)
+        .map(|name| name.as_str().to_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+
+    for expected in [
+        "CollidersBottomWall",
+        "CollidersCircle",
+        "CollidersRegPolygon",
+    ] {
+        assert!(
+            names.binary_search(&expected.to_owned()).is_ok(),
+            "{expected} was not spawned from the level"
+        );
+    }
+}
+
+#[test]
+fn magnets_stay_inside_walls() {
+    let mut lab = HeadlessLab::new(DemoState::Magnet);
+    lab.run_ticks(600);
+
+    let world = lab.world();
+    for transform in world
+        .query_filtered::<&Transform, With<Magnet>>()
+        .iter(world)
+    {
+        let pos = transform.translation.xy();
+        assert!(
+            (-100.0..100.0).contains(&pos.x) && (-55.0..115.0).contains(&pos.y),
+            "magnet escaped the walls: {pos}"
+        );
+    }
+}
+
+#[test]
+fn static_hex_grid_does_not_move() {
+    let mut lab = HeadlessLab::new(DemoState::Destructible);
+
+    let static_hexagons = |world: &mut World| {
+        let mut positions = world
+            .query_filtered::<(Entity, &RigidBody, &Transform), With<Destructible>>()
+            .iter(world)
+            .filter(|(_, rigid_body, _)| rigid_body.is_static())
+            .map(|(entity, _, transform)| (entity, transform.translation))
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|(entity, _)| *entity);
+        positions
+    };
+
+    let before = static_hexagons(lab.world());
+    assert_eq!(before.len(), 100);
+
+    lab.run_ticks(300);
+
+    assert_eq!(before, static_hexagons(lab.world()));
+}
+
+#[test]
+fn platform_riders_do_not_gain_speed() {
+    let mut lab = HeadlessLab::new(DemoState::Home);
+
+    // on top of HomePlatformMove, which starts at (75, -20) and is 10 high
+    let rider = lab
+        .world()
+        .spawn((
+            RigidBody::Dynamic,
+            Collider::rectangle(8.0, 8.0),
+            TransformBundle::from_transform(Transform::from_xyz(75.0, -11.0, 0.0)),
+        ))
+        .id();
+
+    // the platform tops out at 30 units/s, stacked carries would go well past that
+    let mut max_speed: f32 = 0.0;
+    for _ in 0..900 {
+        lab.run_ticks(1);
+        if let Some(velocity) = lab.world().get::<LinearVelocity>(rider) {
+            max_speed = max_speed.max(velocity.length());
+        }
+    }
+
+    assert!(
+        max_speed < 60.0,
+        "rider on a moving platform reached {max_speed} units/s"
+    );
+}
+
+#[test]
+fn replay_matches_recording() {
+    let path = std::env::temp_dir().join("bevy_physics_lab_replay_test.ron");
+    let mut lab = HeadlessLab::new(DemoState::Home);
+
+    // sessions begin at the end of the frame they're requested in
+    lab.world().send_event(StartRecording);
+    lab.run_ticks(1);
+    lab.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyD);
+    lab.run_ticks(120);
+    lab.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .release(KeyCode::KeyD);
+    lab.run_ticks(60);
+    lab.world().send_event(StopRecording { path: path.clone() });
+    lab.run_ticks(1);
+
+    let recording = read_recording(&path).expect("recording was not saved");
+    assert!(recording.ticks.len() > 180);
+
+    // the recording's tuning has to win over the live settings
+    lab.world().insert_resource(GravityFactor { factor: 10.0 });
+    let diverged = replay_diverged_ticks(&mut lab, &path);
+
+    assert!(diverged.is_empty(), "replay diverged at ticks {diverged:?}");
+}
+
+#[test]
+fn replay_holds_actions_without_repressing_them() {
+    let path = std::env::temp_dir().join("bevy_physics_lab_replay_hold_test.ron");
+    let mut lab = HeadlessLab::new(DemoState::Home);
+
+    // spin and grab only act on a fresh press, a held key repressed every tick would show
+    lab.world().send_event(StartRecording);
+    lab.run_ticks(1);
+    lab.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::ArrowRight);
+    lab.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::KeyE);
+    lab.run_ticks(60);
+    lab.world()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .release_all();
+    lab.run_ticks(30);
+    lab.world().send_event(StopRecording { path: path.clone() });
+    lab.run_ticks(1);
+
+    let diverged = replay_diverged_ticks(&mut lab, &path);
+
+    assert!(diverged.is_empty(), "replay diverged at ticks {diverged:?}");
+}
+
+/// Replays the recording at `path` to the end, then deletes it. Returns the ticks reported
+/// as diverged.
+fn replay_diverged_ticks(lab: &mut HeadlessLab, path: &std::path::Path) -> Vec<usize> {
+    let recording = read_recording(path).expect("recording was not saved");
+    lab.world().send_event(StartReplay {
+        path: path.to_path_buf(),
+    });
+
+    let mut diverged_reader = lab
+        .world()
+        .resource::<Events<ReplayDiverged>>()
+        .get_reader();
+    let mut diverged = Vec::new();
+    for _ in 0..recording.ticks.len() + 2 {
+        lab.run_ticks(1);
+        let events = lab.world().resource::<Events<ReplayDiverged>>();
+        diverged.extend(diverged_reader.read(events).map(|event| event.tick));
+    }
+    std::fs::remove_file(path).ok();
+    diverged
+}
+
+#[test]
+fn snapshot_round_trips_positions_and_materials() {
+    let path = std::env::temp_dir().join("bevy_physics_lab_snapshot_test.ron");
+    let mut lab = HeadlessLab::new(DemoState::Colliders);
+    lab.run_ticks(60);
+
+    let circle = |world: &mut World| {
+        world
+            .query::<(Entity, &Name)>()
+            .iter(world)
+            .find(|(_, name)| name.as_str() == "CollidersCircle")
+            .map(|(entity, _)| entity)
+            .expect("CollidersCircle was not spawned")
+    };
+
+    // static and dynamic friction that differ, so losing either shows up
+    let world = lab.world();
+    let saved_circle = circle(world);
+    world
+        .entity_mut(saved_circle)
+        .insert(Friction::new(0.2).with_static_coefficient(0.8));
+    let saved_transform = *world.get::<Transform>(saved_circle).unwrap();
+
+    let saved = capture_snapshot(world);
+    write_snapshot(&saved, &path).expect("snapshot was not saved");
+    lab.run_ticks(60);
+
+    let loaded = read_snapshot(&path).expect("snapshot was not loaded");
+    std::fs::remove_file(&path).ok();
+    let world = lab.world();
+    restore_snapshot(world, &loaded);
+
+    // respawned entities may come back in another order
+    let bodies = |snapshot: &LabSnapshot| {
+        let mut bodies = snapshot
+            .entities
+            .iter()
+            .map(|entity| {
+                let friction = entity.friction.map(|friction| friction.to_friction());
+                let restitution = entity.restitution.map(|material| material.coefficient);
+                (
+                    entity.translation,
+                    friction.map(|friction| {
+                        (friction.static_coefficient, friction.dynamic_coefficient)
+                    }),
+                    restitution,
+                )
+            })
+            .collect::<Vec<_>>();
+        bodies.sort_by(|a, b| a.0.x.total_cmp(&b.0.x).then(a.0.y.total_cmp(&b.0.y)));
+        bodies
+    };
+    let restored = capture_snapshot(world);
+    assert!(!saved.entities.is_empty());
+    assert_eq!(bodies(&saved), bodies(&restored));
+
+    let restored_circle = circle(world);
+    let friction = world.get::<Friction>(restored_circle).unwrap();
+    assert_eq!(
+        (friction.static_coefficient, friction.dynamic_coefficient),
+        (0.8, 0.2)
+    );
+    assert_eq!(
+        world
+            .get::<Restitution>(restored_circle)
+            .unwrap()
+            .coefficient,
+        0.9
+    );
+    let transform = world.get::<Transform>(restored_circle).unwrap();
+    assert_eq!(transform.translation, saved_transform.translation);
+    // rotations go through an angle, so they may be off in the last bits
+    assert!(transform.rotation.angle_between(saved_transform.rotation) < 1e-4);
+}